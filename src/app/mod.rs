@@ -1,9 +1,24 @@
 use crate::serialize::ApplicationData;
+use atlas_common::crypto::hash::{Context, Digest};
 use atlas_common::error::*;
+use atlas_common::maybe_vec::MaybeVec;
 use atlas_common::node_id::NodeId;
 use atlas_common::ordering::{Orderable, SeqNo};
 use atlas_metrics::benchmarks::BatchMeta;
+use rayon::prelude::*;
+use rayon::ThreadPool;
+#[cfg(feature = "serialize_serde")]
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::hash::Hash;
 use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+pub mod routed;
 
 /// Request type of the `Service`.
 pub type Request<A, S> = <<A as Application<S>>::AppData as ApplicationData>::Request;
@@ -26,12 +41,32 @@ pub trait Application<S>: Send + Sync {
     /// Returns the initial state of the application.
     fn initial_state() -> Result<S>;
 
+    /// Prepares the application to serve requests with the live state.
+    ///
+    /// Nothing in this crate calls this: executors must invoke it exactly once, before
+    /// handing the application its first request, and must abort their startup if it fails.
+    /// Unlike `initial_state()`, this runs with the live state (which may have been
+    /// installed through state transfer), so it is the place to warm caches or open
+    /// connections.
+    fn on_start(&self, _state: &mut S) -> Result<()> {
+        Ok(())
+    }
+
+    /// The name of this application, used to tag logs and metrics when
+    /// multiple applications run in the same process.
+    fn name(&self) -> &'static str {
+        "unnamed-application"
+    }
+
     /// Process an unordered client request, and produce a matching reply
     /// Cannot alter the application state
     fn unordered_execution(&self, state: &S, request: Request<Self, S>) -> Reply<Self, S>;
 
     /// Much like [`unordered_execution()`], but processes a batch of requests.
     ///
+    /// The default implementation executes the requests serially, ignoring the
+    /// batch's [`UnorderedBatch::max_parallelism()`] hint.
+    ///
     /// If [`unordered_batched_execution()`] is defined by the user, then [`unordered_execution()`] may
     /// simply be defined as such:
     ///
@@ -58,10 +93,100 @@ pub trait Application<S>: Send + Sync {
         reply_batch
     }
 
+    /// Whether this application has a meaningful read only path, in `unordered_execution()`.
+    ///
+    /// Applications that only mutate their state should return `false`, so the front-end can
+    /// reject or reroute unordered requests, and the executor can treat them as ordered.
+    fn supports_unordered(&self) -> bool {
+        true
+    }
+
+    /// Much like `unordered_batched_execution()`, but the replies are grouped by the node
+    /// they are destined to, preserving the order of the replies of each node.
+    ///
+    /// By default, this executes the batch with `unordered_batched_execution()` and then
+    /// groups the resulting replies.
+    fn unordered_batched_execution_grouped(
+        &self,
+        state: &S,
+        requests: UnorderedBatch<Request<Self, S>>,
+    ) -> HashMap<NodeId, BatchReplies<Reply<Self, S>>> {
+        self.unordered_batched_execution(state, requests)
+            .group_by_node()
+            .into_iter()
+            .map(|(node, replies)| (node, BatchReplies::from(replies)))
+            .collect()
+    }
+
+    /// Serve a read of the committed application state, requested by the node `from`.
+    /// Cannot alter the application state
+    ///
+    /// Returns `None` if the application does not serve reads, which is the default.
+    fn read(&self, _state: &S, _from: NodeId) -> Option<Reply<Self, S>> {
+        None
+    }
+
+    /// Serve a read of the committed application state, requested by the node `from`
+    /// and parameterized by `query`, if any.
+    /// Cannot alter the application state
+    ///
+    /// By default, reads without a query are served by `read()`, while queries are
+    /// served by `unordered_execution()`, as they too cannot alter the state.
+    fn read_query(
+        &self,
+        state: &S,
+        from: NodeId,
+        query: Option<Request<Self, S>>,
+    ) -> Option<Reply<Self, S>> {
+        match query {
+            None => self.read(state, from),
+            Some(query) => Some(self.unordered_execution(state, query)),
+        }
+    }
+
     /// Process a user request, producing a matching reply,
     /// meanwhile updating the application state.
     fn update(&self, state: &mut S, request: Request<Self, S>) -> Reply<Self, S>;
 
+    /// Invoked before the requests of the batch with sequence number `seq` are executed.
+    ///
+    /// Useful, for example, to open a transaction which is then committed in `post_batch()`.
+    fn pre_batch(&self, _state: &mut S, _seq: SeqNo) {}
+
+    /// Invoked after all the requests of the batch with sequence number `seq` have been executed.
+    fn post_batch(&self, _state: &mut S, _seq: SeqNo) {}
+
+    /// A deterministic seed for the batch with sequence number `seq`, with which
+    /// applications that need randomness during execution should seed their PRNG.
+    ///
+    /// Randomness that is not derived deterministically (e.g. from the OS) diverges
+    /// across replicas and breaks replication. The sequence number of the batch being
+    /// executed is available through the batch itself and is handed to `pre_batch()`.
+    fn seed_for(&self, seq: SeqNo) -> u64 {
+        // SplitMix64 finalizer, to spread consecutive sequence numbers across the seed space
+        let mut seed = u64::from(u32::from(seq)).wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        seed = (seed ^ (seed >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        seed = (seed ^ (seed >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        seed ^ (seed >> 31)
+    }
+
+    /// Never invoked unless `wants_request_timing()` is overridden to return true.
+    ///
+    /// When it is, the default `update_batch()` invokes this after each request is executed,
+    /// with the operation id of the request and how long its execution took, allowing the
+    /// execution latency of requests to be fed into metrics.
+    fn on_request_executed(&self, _op_id: SeqNo, _duration: Duration) {}
+
+    /// Whether the default `update_batch()` should time each request and report it to
+    /// `on_request_executed()`. Applications implementing that hook must return true here.
+    ///
+    /// Defaults to false, so requests are not timed unless the hook is in use.
+    fn wants_request_timing(&self) -> bool {
+        false
+    }
+
     /// Much like `update()`, but processes a batch of requests.
     ///
     /// If `update_batch()` is defined by the user, then `update()` may
@@ -80,29 +205,246 @@ pub trait Application<S>: Send + Sync {
         state: &mut S,
         batch: UpdateBatch<Request<Self, S>>,
     ) -> BatchReplies<Reply<Self, S>> {
+        let seq = batch.sequence_number();
         let mut reply_batch = BatchReplies::with_capacity(batch.len());
 
+        let timed = self.wants_request_timing();
+
+        self.pre_batch(state, seq);
+
         for update in batch.into_inner() {
             let (peer_id, sess, opid, req) = update.into_inner();
-            let reply = self.update(state, req);
+
+            let reply = if timed {
+                let start = Instant::now();
+                let reply = self.update(state, req);
+                self.on_request_executed(opid, start.elapsed());
+
+                reply
+            } else {
+                self.update(state, req)
+            };
+
             reply_batch.add(peer_id, sess, opid, reply);
         }
 
+        self.post_batch(state, seq);
+
+        reply_batch
+    }
+
+    /// Much like `update_batch()`, but the requests are executed purely for their effects
+    /// on the state, as no replies are wanted.
+    ///
+    /// By default this calls `update_batch()` and drops the replies, but implementations
+    /// may override it to skip building the replies altogether.
+    fn update_batch_no_reply(&self, state: &mut S, batch: UpdateBatch<Request<Self, S>>) {
+        let _ = self.update_batch(state, batch);
+    }
+
+    /// Much like `update_batch()`, but each reply is handed to `sink` as it is produced,
+    /// in execution order, instead of being collected. This allows the executor to
+    /// deliver the replies of long running batches incrementally.
+    ///
+    /// By default this calls `update_batch()` and feeds the resulting replies to `sink`,
+    /// so replies are only delivered once the whole batch has executed. Implementations
+    /// should override it to actually stream them.
+    fn update_batch_streaming(
+        &self,
+        state: &mut S,
+        batch: UpdateBatch<Request<Self, S>>,
+        sink: &mut dyn FnMut(UpdateReply<Reply<Self, S>>),
+    ) {
+        for reply in self.update_batch(state, batch).into_inner() {
+            sink(reply);
+        }
+    }
+
+    /// Replays a run of ordered batches, used when catching up to the rest of the quorum.
+    ///
+    /// By default, each batch is executed with `update_batch()`. As replies are not needed
+    /// while catching up, overriding implementations may skip generating them and return
+    /// empty replies instead.
+    fn update_catch_up(
+        &self,
+        state: &mut S,
+        batches: MaybeVec<UpdateBatch<Request<Self, S>>>,
+    ) -> MaybeVec<BatchReplies<Reply<Self, S>>> {
+        let replies = batches
+            .into_iter()
+            .map(|batch| self.update_batch(state, batch))
+            .collect();
+
+        MaybeVec::from_many(replies)
+    }
+
+    /// How often the executor should take an application state checkpoint.
+    ///
+    /// By default checkpoints are only taken when requested (see [`CheckpointPolicy::Manual`]).
+    fn checkpoint_policy(&self) -> CheckpointPolicy {
+        CheckpointPolicy::Manual
+    }
+
+    /// Whether the operations of this application are known to commute, meaning
+    /// the requests of a batch may be executed in any order (and in parallel)
+    /// while still producing the same state and replies.
+    ///
+    /// Parallel execution additionally requires implementing [`CommutativeApplication`].
+    fn is_commutative(&self) -> bool {
+        false
+    }
+
+    /// Fallible version of `update()`, allowing the application to abort a request
+    /// (e.g. on an invariant violation) instead of producing a regular reply.
+    ///
+    /// Implementations that override this must leave `state` untouched when returning `Err`.
+    ///
+    /// By default, this just delegates to `update()`, which never fails.
+    fn try_update(&self, state: &mut S, request: Request<Self, S>) -> Result<Reply<Self, S>> {
+        Ok(self.update(state, request))
+    }
+
+    /// Much like `try_update()`, but processes a batch of requests, collecting
+    /// the outcome of each request (see [`ReplyOutcome`]'s conversion from a `Result`).
+    ///
+    /// A failed request does not stop the execution of the remaining requests in the batch.
+    fn try_update_batch(
+        &self,
+        state: &mut S,
+        batch: UpdateBatch<Request<Self, S>>,
+    ) -> BatchReplies<ReplyOutcome<Reply<Self, S>>> {
+        let seq = batch.sequence_number();
+        let mut reply_batch = BatchReplies::with_capacity(batch.len());
+
+        self.pre_batch(state, seq);
+
+        for update in batch.into_inner() {
+            let (peer_id, sess, opid, req) = update.into_inner();
+            let reply = self.try_update(state, req);
+            reply_batch.add_result(peer_id, sess, opid, reply.into());
+        }
+
+        self.post_batch(state, seq);
+
         reply_batch
     }
+
+    /// Executes `batch` against a copy of `state`, to detect whether any of its requests
+    /// would be rejected before actually committing to it.
+    ///
+    /// The requests are executed with `try_update()` and the copy of the state is discarded
+    /// afterwards, so `state` is never mutated. Returns the error of the first rejected request.
+    ///
+    /// `pre_batch()` and `post_batch()` are not invoked, as hooks may have effects outside
+    /// of the state (e.g. metrics or logging) which must not be observed for a batch that
+    /// is not actually executed.
+    ///
+    /// Only available for applications whose state (and requests) can be cloned.
+    fn dry_run_batch(&self, state: &S, batch: &UpdateBatch<Request<Self, S>>) -> Result<()>
+    where
+        S: Clone,
+        Request<Self, S>: Clone,
+    {
+        let mut scratch = state.clone();
+
+        for update in batch.iter() {
+            self.try_update(&mut scratch, update.operation().clone())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An application whose operations are known to commute (see [`Application::is_commutative()`]),
+/// so the requests of a batch can be executed in parallel.
+pub trait CommutativeApplication<S>: Application<S> {
+    /// Process a user request, producing a matching reply,
+    /// meanwhile updating the application state through a shared reference.
+    ///
+    /// As requests may be executed concurrently, the concurrency control must be
+    /// done by the state.
+    fn update_commutative(&self, state: &S, request: Request<Self, S>) -> Reply<Self, S>;
+
+    /// The pool the requests of a batch are executed on by `execute_batch_parallel()`.
+    ///
+    /// Defaults to `None`, which uses rayon's global pool.
+    fn execution_pool(&self) -> Option<&ThreadPool> {
+        None
+    }
+
+    /// Much like `update_batch()`, but when the application [`Application::is_commutative()`],
+    /// the requests are executed in parallel with `update_commutative()`, on the
+    /// [`Self::execution_pool()`].
+    ///
+    /// Executors must call this explicitly: the default `update_batch()` always executes
+    /// requests serially and never dispatches here, even for commutative applications.
+    ///
+    /// Regardless of the order in which the requests are executed, the replies
+    /// are returned in the original request order.
+    fn execute_batch_parallel(
+        &self,
+        state: &mut S,
+        batch: UpdateBatch<Request<Self, S>>,
+    ) -> BatchReplies<Reply<Self, S>>
+    where
+        S: Sync,
+        Request<Self, S>: Send,
+        Reply<Self, S>: Send,
+    {
+        if !self.is_commutative() {
+            return self.update_batch(state, batch);
+        }
+
+        let seq = batch.sequence_number();
+
+        self.pre_batch(state, seq);
+
+        let shared_state: &S = state;
+
+        let execute = || {
+            batch
+                .into_inner()
+                .into_par_iter()
+                .map(|update| {
+                    let (peer_id, sess, opid, req) = update.into_inner();
+                    let reply = self.update_commutative(shared_state, req);
+
+                    UpdateReply::init(peer_id, sess, opid, reply)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let replies = match self.execution_pool() {
+            Some(pool) => pool.install(execute),
+            None => execute(),
+        };
+
+        self.post_batch(state, seq);
+
+        BatchReplies::from(replies)
+    }
 }
 
 /// Represents a single client update request, to be executed.
 #[derive(Clone)]
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
 pub struct Update<O> {
     from: NodeId,
     session_id: SeqNo,
     operation_id: SeqNo,
     operation: O,
+    /// A deadline local to the replica that received the request, only meaningful to the
+    /// proposer (see [`UpdateBatch::drop_expired()`]), so it is not serialized
+    #[cfg_attr(feature = "serialize_serde", serde(skip))]
+    deadline: Option<Instant>,
+    /// The sequence number of the batch this operation must be executed after, if any,
+    /// for cross batch causal ordering
+    depends_on: Option<SeqNo>,
 }
 
 /// Represents a single client update reply.
 #[derive(Clone)]
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
 pub struct UpdateReply<P> {
     to: NodeId,
     session_id: SeqNo,
@@ -112,20 +454,211 @@ pub struct UpdateReply<P> {
 
 /// Storage for a batch of client update requests to be executed.
 #[derive(Clone)]
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
 pub struct UnorderedBatch<O> {
     inner: Vec<Update<O>>,
+    max_parallelism: Option<usize>,
+}
+
+/// Operations that can be fed into the fingerprint of a batch (see [`UpdateBatch::fingerprint()`])
+pub trait Fingerprint {
+    /// Feeds a deterministic representation of this operation into `ctx`.
+    /// Equal operations must always produce the same representation, on every replica.
+    fn fingerprint(&self, ctx: &mut Context);
+}
+
+impl Fingerprint for Vec<u8> {
+    fn fingerprint(&self, ctx: &mut Context) {
+        ctx.update(&(self.len() as u64).to_le_bytes());
+        ctx.update(self);
+    }
+}
+
+/// Types whose size in memory (including any heap allocations they own) can be estimated
+pub trait SizeOf {
+    /// The approximate amount of bytes taken up by this value
+    fn size_of(&self) -> usize;
+}
+
+impl SizeOf for [u8] {
+    fn size_of(&self) -> usize {
+        self.len()
+    }
+}
+
+impl SizeOf for Vec<u8> {
+    fn size_of(&self) -> usize {
+        std::mem::size_of::<Self>() + self.len()
+    }
+}
+
+impl SizeOf for Box<[u8]> {
+    fn size_of(&self) -> usize {
+        std::mem::size_of::<Self>() + self.len()
+    }
+}
+
+impl SizeOf for String {
+    fn size_of(&self) -> usize {
+        std::mem::size_of::<Self>() + self.len()
+    }
+}
+
+/// Walks the updates of a batch without consuming or cloning it (see [`UpdateBatch::visit()`]),
+/// e.g. for auditing or policy enforcement.
+pub trait UpdateVisitor<O> {
+    /// Invoked once for every update, in batch order.
+    fn visit(&mut self, from: NodeId, session_id: SeqNo, operation_id: SeqNo, operation: &O);
+}
+
+/// Operations that carry a key identifying them across retries, used to guarantee they
+/// are only applied once (see [`UpdateBatch::dedup_idempotent()`])
+pub trait IdempotentOperation {
+    /// The key identifying an operation.
+    type IdKey: Eq + Hash;
+
+    /// The idempotency key of this operation, or `None` if it may safely be applied more
+    /// than once.
+    fn idempotency_key(&self) -> Option<Self::IdKey>;
+}
+
+/// Errors produced when manipulating batches
+#[derive(Error, Debug)]
+pub enum BatchError {
+    #[error("Cannot merge batch {other:?} into batch {current:?}, as it precedes it")]
+    MergeOutOfOrder { current: SeqNo, other: SeqNo },
+    #[error("Cannot merge a batch of epoch {other} into a batch of epoch {current}")]
+    MergeEpochMismatch { current: u64, other: u64 },
+    #[error("Operation {operation:?} of node {from:?} depends on batch {depends_on:?}, but only batches up to {executed_up_to:?} were executed")]
+    UnsatisfiedDependency {
+        from: NodeId,
+        operation: SeqNo,
+        depends_on: SeqNo,
+        executed_up_to: SeqNo,
+    },
+    #[error("Session {session:?} of node {to:?} is replied operation {operation:?} after operation {previous:?}")]
+    ReplyOrderViolation {
+        to: NodeId,
+        session: SeqNo,
+        previous: SeqNo,
+        operation: SeqNo,
+    },
+    #[error("The batch is full, as it holds its maximum of {max} updates")]
+    BatchFull { max: usize },
+    #[error("Session {session:?} of node {from:?} has operation {operation:?} after operation {previous:?}")]
+    SessionOrderViolation {
+        from: NodeId,
+        session: SeqNo,
+        previous: SeqNo,
+        operation: SeqNo,
+    },
+    #[error("The batch contains duplicated operations (from, session, operation): {0:?}")]
+    DuplicateOperations(Vec<(NodeId, SeqNo, SeqNo)>),
+}
+
+/// Error produced when transforming the operations of a batch fails,
+/// identifying the update which failed
+#[derive(Error, Debug)]
+#[error("Failed to map the operation of the update at index {index}: {error}")]
+pub struct OperationMapError<E> {
+    index: usize,
+    error: E,
+}
+
+impl<E> OperationMapError<E> {
+    /// The index, within the batch, of the update whose operation failed to be mapped
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    pub fn into_error(self) -> E {
+        self.error
+    }
+}
+
+/// The priority tier of an ordered batch.
+///
+/// Lets a scheduling layer reorder `Normal`/`Low` work around `High` work.
+/// Tiers are ordered from lowest to highest priority.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+pub enum BatchPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// The cadence at which the executor takes application state checkpoints
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CheckpointPolicy {
+    /// Checkpoint after every `n` executed batches
+    EveryNBatches(u32),
+    /// Checkpoint once `n` operations have been executed since the last checkpoint
+    EveryNOps(u64),
+    /// Only checkpoint when explicitly requested
+    #[default]
+    Manual,
+}
+
+impl CheckpointPolicy {
+    /// Whether a checkpoint is due, given the amount of batches and operations
+    /// executed since the last one. A policy with a period of 0 never triggers.
+    pub fn should_checkpoint(&self, batches_since_last: u64, ops_since_last: u64) -> bool {
+        match *self {
+            CheckpointPolicy::EveryNBatches(n) => n > 0 && batches_since_last >= u64::from(n),
+            CheckpointPolicy::EveryNOps(n) => n > 0 && ops_since_last >= n,
+            CheckpointPolicy::Manual => false,
+        }
+    }
 }
 
 /// Storage for a batch of client update requests to be executed.
 #[derive(Clone)]
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
 pub struct UpdateBatch<O> {
     seq_no: SeqNo,
     inner: Vec<Update<O>>,
+    #[cfg_attr(feature = "serialize_serde", serde(skip))]
     meta: Option<BatchMeta>,
+    priority: BatchPriority,
+    #[cfg_attr(feature = "serialize_serde", serde(skip))]
+    proposed_at: Option<Instant>,
+    epoch: Option<u64>,
+    /// The maximum amount of updates this batch accepts through `try_add()`, if bounded
+    #[cfg_attr(feature = "serialize_serde", serde(skip))]
+    max_len: Option<usize>,
+    /// An opaque, serialized tracing span context, so a trace can be continued
+    /// across the consensus boundary
+    trace_context: Option<Vec<u8>>,
+}
+
+/// Errors an application can reply with, instead of a regular reply
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+pub enum ExecutionError {
+    #[error("The request was rejected by the application: {0}")]
+    Rejected(String),
+    #[error("The request is malformed: {0}")]
+    Malformed(String),
+}
+
+/// The outcome of executing a single request, distinguishing
+/// successful replies from errors
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+pub enum ReplyOutcome<P> {
+    Ok(P),
+    Err(ExecutionError),
 }
 
 /// Storage for a batch of client update replies.
 #[derive(Clone)]
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
 pub struct BatchReplies<P> {
     inner: Vec<UpdateReply<P>>,
 }
@@ -137,78 +670,133 @@ impl<O> UpdateBatch<O> {
             seq_no,
             inner: Vec::new(),
             meta: None,
+            priority: BatchPriority::default(),
+            proposed_at: None,
+            epoch: None,
+            max_len: None,
+            trace_context: None,
         }
     }
 
     pub fn new_with_cap(seq_no: SeqNo, capacity: usize) -> Self {
         Self {
-            seq_no,
             inner: Vec::with_capacity(capacity),
+            ..Self::new(seq_no)
+        }
+    }
+
+    /// A batch holding `inner`, with every other field of this batch but the metadata.
+    ///
+    /// Every batch derived from this one is built through here, so new fields are
+    /// carried over everywhere at once.
+    fn with_inner<U>(&self, inner: Vec<Update<U>>) -> UpdateBatch<U> {
+        UpdateBatch {
+            seq_no: self.seq_no,
+            inner,
             meta: None,
+            priority: self.priority,
+            proposed_at: self.proposed_at,
+            epoch: self.epoch,
+            max_len: self.max_len,
+            trace_context: self.trace_context.clone(),
         }
     }
 
-    /// Adds a new update request to the batch.
-    pub fn add(&mut self, from: NodeId, session_id: SeqNo, operation_id: SeqNo, operation: O) {
-        self.inner.push(Update {
-            from,
-            session_id,
-            operation_id,
-            operation,
-        });
+    /// Returns a new, empty batch which accepts at most `max` updates through `try_add()`.
+    ///
+    /// The bound is only enforced by `try_add()`, the infallible `add()` (as well as
+    /// merging) ignores it. No room is reserved up front, as `max` is a limit
+    /// rather than the expected size of the batch.
+    pub fn new_bounded(seq_no: SeqNo, max: usize) -> Self {
+        Self {
+            max_len: Some(max),
+            ..Self::new(seq_no)
+        }
     }
 
-    /// Returns the inner storage.
-    pub fn into_inner(self) -> Vec<Update<O>> {
-        self.inner
+    /// The maximum amount of updates this batch accepts through `try_add()`, if bounded.
+    pub fn max_len(&self) -> Option<usize> {
+        self.max_len
     }
 
-    /// Returns the length of the batch.
-    pub fn len(&self) -> usize {
-        self.inner.len()
+    /// Attaches an opaque, serialized tracing span context to this batch, so the executor
+    /// can continue the trace. Its format is up to the tracing backend.
+    pub fn with_trace_context(mut self, trace_context: Vec<u8>) -> Self {
+        self.trace_context = Some(trace_context);
+        self
     }
 
-    pub fn append_batch_meta(&mut self, batch_meta: BatchMeta) {
-        let _ = self.meta.insert(batch_meta);
+    pub fn trace_context(&self) -> Option<&[u8]> {
+        self.trace_context.as_deref()
     }
 
-    pub fn take_meta(&mut self) -> Option<BatchMeta> {
-        self.meta.take()
+    /// Sets the priority tier of this batch.
+    pub fn with_priority(mut self, priority: BatchPriority) -> Self {
+        self.priority = priority;
+        self
     }
-}
 
-impl<O> Orderable for UpdateBatch<O> {
-    fn sequence_number(&self) -> SeqNo {
-        self.seq_no
+    /// Returns the priority tier of this batch ([`BatchPriority::Normal`] unless set).
+    pub fn priority(&self) -> BatchPriority {
+        self.priority
     }
-}
 
-impl<O> Default for UnorderedBatch<O> {
-    fn default() -> Self {
-        Self::new()
+    /// Sets the instant at which this batch was proposed.
+    pub fn with_proposed_at(mut self, proposed_at: Instant) -> Self {
+        self.proposed_at = Some(proposed_at);
+        self
     }
-}
 
-impl<O> UnorderedBatch<O> {
-    /// Returns a new, empty batch of requests.
-    pub fn new() -> Self {
-        Self { inner: Vec::new() }
+    /// The instant at which this batch was proposed, if known.
+    /// Allows applications to account for the end to end latency of a batch.
+    pub fn proposed_at(&self) -> Option<Instant> {
+        self.proposed_at
     }
 
-    pub fn new_with_cap(capacity: usize) -> Self {
-        Self {
-            inner: Vec::with_capacity(capacity),
-        }
+    /// Tags this batch with an application defined configuration epoch.
+    pub fn with_epoch(mut self, epoch: u64) -> Self {
+        self.epoch = Some(epoch);
+        self
+    }
+
+    /// The configuration epoch this batch was tagged with, if any.
+    pub fn epoch(&self) -> Option<u64> {
+        self.epoch
+    }
+
+    /// Sets the instant at which this batch was proposed, unless it is already set.
+    pub(crate) fn stamp_proposed_at(&mut self, proposed_at: Instant) {
+        let _ = self.proposed_at.get_or_insert(proposed_at);
     }
 
     /// Adds a new update request to the batch.
     pub fn add(&mut self, from: NodeId, session_id: SeqNo, operation_id: SeqNo, operation: O) {
-        self.inner.push(Update {
-            from,
-            session_id,
-            operation_id,
-            operation,
-        });
+        self.push(Update::new(from, session_id, operation_id, operation));
+    }
+
+    /// Adds an already built update to the batch, keeping its deadline (if any).
+    pub fn push(&mut self, update: Update<O>) {
+        self.inner.push(update);
+    }
+
+    /// Same as `add()`, but fails if this batch is bounded (see `new_bounded()`) and
+    /// already holds the maximum amount of updates.
+    pub fn try_add(
+        &mut self,
+        from: NodeId,
+        session_id: SeqNo,
+        operation_id: SeqNo,
+        operation: O,
+    ) -> Result<()> {
+        if let Some(max) = self.max_len {
+            if self.inner.len() >= max {
+                return Err(BatchError::BatchFull { max }.into());
+            }
+        }
+
+        self.add(from, session_id, operation_id, operation);
+
+        Ok(())
     }
 
     /// Returns the inner storage.
@@ -216,127 +804,3346 @@ impl<O> UnorderedBatch<O> {
         self.inner
     }
 
+    /// Takes the inner storage, leaving this batch empty (with the same sequence number)
+    /// so it can be reused.
+    pub fn take_inner(&mut self) -> Vec<Update<O>> {
+        std::mem::take(&mut self.inner)
+    }
+
     /// Returns the length of the batch.
     pub fn len(&self) -> usize {
         self.inner.len()
     }
-}
 
-impl<O> AsRef<[Update<O>]> for UpdateBatch<O> {
-    fn as_ref(&self) -> &[Update<O>] {
-        &self.inner[..]
+    /// Returns whether the batch holds no requests.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
     }
-}
 
-impl<O> Update<O> {
-    /// Returns the inner types stored in this `Update`.
-    pub fn into_inner(self) -> (NodeId, SeqNo, SeqNo, O) {
-        (
-            self.from,
-            self.session_id,
-            self.operation_id,
-            self.operation,
-        )
+    /// Whether a checkpoint should be taken after executing this batch, given
+    /// `updates_since_last` updates were executed since the last checkpoint, before it.
+    ///
+    /// See [`CheckpointPolicy::EveryNOps`] for the policy based equivalent.
+    pub fn suggest_checkpoint(&self, updates_since_last: usize, threshold: usize) -> bool {
+        threshold > 0 && updates_since_last + self.len() >= threshold
     }
 
-    /// Returns a reference to this operation in this `Update`.
-    pub fn operation(&self) -> &O {
-        &self.operation
+    /// Retains only the updates for which `f` returns `true`, preserving their order.
+    ///
+    /// The sequence number and metadata of the batch are left untouched.
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&Update<O>) -> bool,
+    {
+        self.inner.retain(f);
     }
 
-    pub fn from(&self) -> NodeId {
-        self.from
+    /// Removes later duplicates of an operation, identified by its `(from, session_id, operation_id)`,
+    /// keeping the first occurrence and the order of the remaining updates.
+    ///
+    /// This is never done automatically, so deterministic replay is preserved.
+    /// Returns the amount of updates that were removed.
+    pub fn dedup_by_operation(&mut self) -> usize {
+        dedup_updates(&mut self.inner)
     }
 
-    pub fn session_id(&self) -> SeqNo {
-        self.session_id
+    /// The set of nodes that contributed requests to this batch, sorted by their id.
+    pub fn contributing_nodes(&self) -> BTreeSet<NodeId> {
+        self.inner.iter().map(Update::from).collect()
     }
 
-    pub fn operation_id(&self) -> SeqNo {
-        self.operation_id
+    pub fn append_batch_meta(&mut self, batch_meta: BatchMeta) {
+        let _ = self.meta.insert(batch_meta);
     }
-}
 
-impl<P> BatchReplies<P> {
-    /*
-        /// Returns a new, empty batch of replies.
-        pub fn new() -> Self {
-            Self { inner: Vec::new() }
+    pub fn take_meta(&mut self) -> Option<BatchMeta> {
+        self.meta.take()
+    }
+
+    /// Returns a mutable reference to the metadata of this batch, initializing it if absent.
+    ///
+    /// Allows execution hooks to stamp their own timings into the metadata.
+    pub fn with_meta_mut(&mut self) -> &mut BatchMeta {
+        self.meta.get_or_insert_with(BatchMeta::new)
+    }
+
+    /// Returns an iterator over the updates in this batch, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &Update<O>> {
+        self.inner.iter()
+    }
+
+    /// Returns an iterator that allows modifying each update in this batch, in order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Update<O>> {
+        self.inner.iter_mut()
+    }
+
+    /// Walks the updates in this batch, in order, handing each of them to `visitor`.
+    pub fn visit<V>(&self, visitor: &mut V)
+    where
+        V: UpdateVisitor<O>,
+    {
+        for update in &self.inner {
+            visitor.visit(
+                update.from,
+                update.session_id,
+                update.operation_id,
+                &update.operation,
+            );
         }
-    */
+    }
 
-    /// Returns a new, empty batch of replies, with the given capacity.
-    pub fn with_capacity(n: usize) -> Self {
-        Self {
-            inner: Vec::with_capacity(n),
+    /// Appends the updates of `other` to this batch.
+    ///
+    /// `other` must not precede this batch, and the merged batch takes the greater of the
+    /// two sequence numbers. The metadata of this batch is kept if present, otherwise the one
+    /// of `other` is used, and the same goes for the proposal instant and epoch.
+    /// Batches tagged with different epochs cannot be merged.
+    /// The merged batch takes the highest priority of the two.
+    pub fn merge(&mut self, other: UpdateBatch<O>) -> Result<()> {
+        if other.seq_no < self.seq_no {
+            return Err(BatchError::MergeOutOfOrder {
+                current: self.seq_no,
+                other: other.seq_no,
+            }
+            .into());
+        }
+
+        if let (Some(current), Some(other)) = (self.epoch, other.epoch) {
+            if current != other {
+                return Err(BatchError::MergeEpochMismatch { current, other }.into());
+            }
+        }
+
+        self.seq_no = other.seq_no;
+        self.inner.extend(other.inner);
+        self.priority = self.priority.max(other.priority);
+
+        if self.meta.is_none() {
+            self.meta = other.meta;
+        }
+
+        if self.proposed_at.is_none() {
+            self.proposed_at = other.proposed_at;
+        }
+
+        if self.epoch.is_none() {
+            self.epoch = other.epoch;
+        }
+
+        if self.trace_context.is_none() {
+            self.trace_context = other.trace_context;
         }
+
+        Ok(())
     }
 
-    /// Adds a new update reply to the batch.
-    pub fn add(&mut self, to: NodeId, session_id: SeqNo, operation_id: SeqNo, payload: P) {
-        self.inner.push(UpdateReply {
-            to,
-            session_id,
-            operation_id,
-            payload,
+    /// Transforms the operation of every update in this batch, preserving the
+    /// routing fields of each update as well as the batch's sequence number and metadata.
+    pub fn map_operations<U, F>(mut self, mut f: F) -> UpdateBatch<U>
+    where
+        F: FnMut(O) -> U,
+    {
+        let inner = std::mem::take(&mut self.inner)
+            .into_iter()
+            .map(|update| update.map(&mut f))
+            .collect();
+
+        UpdateBatch {
+            meta: self.meta.take(),
+            ..self.with_inner(inner)
+        }
+    }
+
+    /// Clones the shape of this batch (its sequence number and the routing fields of
+    /// every update) without the operations, which may be large or expensive to clone.
+    ///
+    /// Meant for tracking structures that only care about which requests a batch holds.
+    /// The metadata is not carried over.
+    pub fn clone_metadata_only(&self) -> UpdateBatch<()> {
+        self.with_inner(
+            self.inner
+                .iter()
+                .map(|update| update.as_ref_operation().map(|_| ()))
+                .collect(),
+        )
+    }
+
+    /// Fallible version of `map_operations()`, which stops at the first operation that
+    /// fails to be transformed, reporting its index within the batch.
+    pub fn try_map_operations<U, E, F>(
+        mut self,
+        mut f: F,
+    ) -> std::result::Result<UpdateBatch<U>, OperationMapError<E>>
+    where
+        F: FnMut(O) -> std::result::Result<U, E>,
+    {
+        let mut inner = Vec::with_capacity(self.inner.len());
+
+        for (index, update) in std::mem::take(&mut self.inner).into_iter().enumerate() {
+            let Update {
+                from,
+                session_id,
+                operation_id,
+                operation,
+                deadline,
+                depends_on,
+            } = update;
+
+            let operation = f(operation).map_err(|error| OperationMapError { index, error })?;
+
+            inner.push(Update {
+                from,
+                session_id,
+                operation_id,
+                operation,
+                deadline,
+                depends_on,
+            });
+        }
+
+        Ok(UpdateBatch {
+            meta: self.meta.take(),
+            ..self.with_inner(inner)
+        })
+    }
+
+    /// An estimate of the amount of bytes taken up by this batch: the fixed overhead of
+    /// the batch itself plus, for every update, its routing fields and operation.
+    pub fn size_bytes(&self) -> usize
+    where
+        O: SizeOf,
+    {
+        let routing_overhead = std::mem::size_of::<Update<O>>() - std::mem::size_of::<O>();
+
+        self.inner
+            .iter()
+            .map(|update| routing_overhead + update.operation.size_of())
+            .sum::<usize>()
+            + std::mem::size_of::<Self>()
+    }
+
+    /// Checks that, within each client session (identified by `(from, session_id)`),
+    /// the operation ids are strictly increasing in batch order.
+    ///
+    /// This is a diagnostic which can be run before executing the batch, returning
+    /// an error identifying the first session found out of order.
+    pub fn validate_session_ordering(&self) -> Result<()> {
+        let mut last_operations: HashMap<(NodeId, SeqNo), SeqNo> = HashMap::new();
+
+        for update in &self.inner {
+            let session = (update.from, update.session_id);
+
+            if let Some(previous) = last_operations.insert(session, update.operation_id) {
+                if previous >= update.operation_id {
+                    return Err(BatchError::SessionOrderViolation {
+                        from: update.from,
+                        session: update.session_id,
+                        previous,
+                        operation: update.operation_id,
+                    }
+                    .into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every update that depends on a batch (see [`Update::depends_on()`])
+    /// depends on one that has already been executed, given batches have been executed
+    /// up to (and including) `executed_up_to`.
+    ///
+    /// Returns an error identifying the first update whose dependency is not yet satisfied.
+    pub fn validate_dependencies(&self, executed_up_to: SeqNo) -> Result<()> {
+        let unsatisfied = self.inner.iter().find_map(|update| {
+            update
+                .depends_on
+                .filter(|depends_on| *depends_on > executed_up_to)
+                .map(|depends_on| (update, depends_on))
         });
+
+        match unsatisfied {
+            Some((update, depends_on)) => Err(BatchError::UnsatisfiedDependency {
+                from: update.from,
+                operation: update.operation_id,
+                depends_on,
+                executed_up_to,
+            }
+            .into()),
+            None => Ok(()),
+        }
     }
 
-    pub fn push(&mut self, reply: UpdateReply<P>) {
-        self.inner.push(reply);
+    /// Checks that no operation, identified by its `(from, session_id, operation_id)`,
+    /// appears more than once in this batch.
+    ///
+    /// Unlike `dedup_by_operation()`, this does not modify the batch but instead
+    /// fails with an error listing every duplicated operation.
+    pub fn check_unique_operations(&self) -> Result<()> {
+        let mut seen = HashSet::with_capacity(self.inner.len());
+        let mut reported = HashSet::new();
+
+        let duplicates: Vec<_> = self
+            .inner
+            .iter()
+            .map(Update::operation_key)
+            .filter(|key| !seen.insert(*key) && reported.insert(*key))
+            .collect();
+
+        if duplicates.is_empty() {
+            return Ok(());
+        }
+
+        Err(BatchError::DuplicateOperations(duplicates).into())
     }
 
-    pub fn inner(&self) -> &Vec<UpdateReply<P>> {
-        &self.inner
+    /// Computes a fingerprint of this batch, covering its sequence number and the
+    /// routing fields and operation of every update, in order.
+    ///
+    /// Two replicas that executed the exact same batch produce the same fingerprint,
+    /// which makes it useful to debug divergences.
+    pub fn fingerprint(&self) -> Digest
+    where
+        O: Fingerprint,
+    {
+        let mut ctx = Context::new();
+
+        ctx.update(&u32::from(self.seq_no).to_le_bytes());
+
+        for update in &self.inner {
+            ctx.update(&update.from.0.to_le_bytes());
+            ctx.update(&u32::from(update.session_id).to_le_bytes());
+            ctx.update(&u32::from(update.operation_id).to_le_bytes());
+
+            update.operation.fingerprint(&mut ctx);
+        }
+
+        ctx.finish()
+    }
+
+    /// Drops the operations whose idempotency key is already in `seen`, recording the keys
+    /// of the ones that are kept.
+    ///
+    /// As `seen` is provided by the caller, it can be carried across batches to ensure an
+    /// operation is applied exactly once. Operations without a key are always kept.
+    /// Returns the amount of operations that were dropped.
+    pub fn dedup_idempotent(&mut self, seen: &mut HashSet<O::IdKey>) -> usize
+    where
+        O: IdempotentOperation,
+    {
+        let before = self.inner.len();
+
+        self.inner
+            .retain(|update| match update.operation.idempotency_key() {
+                Some(key) => seen.insert(key),
+                None => true,
+            });
+
+        before - self.inner.len()
+    }
+
+    /// Splits this batch into the updates that match `pred` and the ones that do not,
+    /// preserving their relative order.
+    ///
+    /// Both batches keep the sequence number, priority, proposal instant and epoch of
+    /// this batch, but the metadata goes only to the first (matching) batch.
+    pub fn partition<F>(mut self, pred: F) -> (UpdateBatch<O>, UpdateBatch<O>)
+    where
+        F: FnMut(&Update<O>) -> bool,
+    {
+        let (matching, remaining): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut self.inner).into_iter().partition(pred);
+
+        (
+            UpdateBatch {
+                meta: self.meta.take(),
+                ..self.with_inner(matching)
+            },
+            self.with_inner(remaining),
+        )
+    }
+
+    /// Splits this batch into sub batches of at most `max` updates each, preserving order.
+    ///
+    /// Every chunk shares the sequence number, priority, proposal instant and epoch of the
+    /// original batch, but the metadata is only attached to the first chunk, to avoid
+    /// double counting it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is 0.
+    pub fn split_into_chunks(mut self, max: usize) -> Vec<UpdateBatch<O>> {
+        assert!(max > 0, "Chunk size must be greater than zero");
+
+        let inner = std::mem::take(&mut self.inner);
+
+        let mut chunks = Vec::with_capacity(inner.len().div_ceil(max).max(1));
+        let mut updates = inner.into_iter().peekable();
+
+        while chunks.is_empty() || updates.peek().is_some() {
+            chunks.push(UpdateBatch {
+                meta: self.meta.take(),
+                ..self.with_inner(updates.by_ref().take(max).collect())
+            });
+        }
+
+        chunks
+    }
+
+    /// Sorts the updates of this batch into their canonical order (see [`Update::cmp_canonical()`]).
+    ///
+    /// This is opt in, as it discards the order established by the ordering protocol.
+    pub fn sort_canonical(&mut self) {
+        self.inner.sort_by(Update::cmp_canonical);
+    }
+
+    /// Removes every update whose deadline is before `now`, preserving the order of the
+    /// remaining updates.
+    ///
+    /// Must only be used on the proposer side, before the batch is proposed for ordering.
+    /// Deadlines are instants of the local clock of the replica that received the requests,
+    /// so dropping updates from an already ordered batch would make each replica execute
+    /// a different set of updates, diverging their states. Deadlines are not serialized,
+    /// so batches received from the ordering protocol carry none.
+    ///
+    /// Returns the amount of updates that were dropped.
+    pub fn drop_expired(&mut self, now: Instant) -> usize {
+        let before = self.inner.len();
+
+        self.inner.retain(|update| !update.is_expired(now));
+
+        before - self.inner.len()
+    }
+}
+
+impl<O> Orderable for UpdateBatch<O> {
+    fn sequence_number(&self) -> SeqNo {
+        self.seq_no
+    }
+}
+
+impl<O> Default for UnorderedBatch<O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O> UnorderedBatch<O> {
+    /// Returns a new, empty batch of requests.
+    pub fn new() -> Self {
+        Self {
+            inner: Vec::new(),
+            max_parallelism: None,
+        }
+    }
+
+    pub fn new_with_cap(capacity: usize) -> Self {
+        Self {
+            inner: Vec::with_capacity(capacity),
+            max_parallelism: None,
+        }
+    }
+
+    /// Sets a soft limit on how many of these requests should be executed concurrently.
+    pub fn with_max_parallelism(mut self, max_parallelism: usize) -> Self {
+        self.max_parallelism = Some(max_parallelism);
+        self
+    }
+
+    /// The soft limit on how many of these requests should be executed concurrently, if any.
+    ///
+    /// This is only a hint for applications that execute unordered requests in parallel,
+    /// the default (serial) execution ignores it.
+    pub fn max_parallelism(&self) -> Option<usize> {
+        self.max_parallelism
+    }
+
+    /// Adds a new update request to the batch.
+    pub fn add(&mut self, from: NodeId, session_id: SeqNo, operation_id: SeqNo, operation: O) {
+        self.inner
+            .push(Update::new(from, session_id, operation_id, operation));
     }
 
     /// Returns the inner storage.
-    pub fn into_inner(self) -> Vec<UpdateReply<P>> {
+    pub fn into_inner(self) -> Vec<Update<O>> {
         self.inner
     }
 
+    /// Takes the inner storage, leaving this batch empty so it can be reused.
+    pub fn take_inner(&mut self) -> Vec<Update<O>> {
+        std::mem::take(&mut self.inner)
+    }
+
+    /// Splits this batch into runs of consecutive updates which map to the same `key`,
+    /// keeping the order of the updates. Every run keeps the parallelism hint of this batch.
+    pub fn split_into_runs<K, F>(self, mut key: F) -> Vec<(K, UnorderedBatch<O>)>
+    where
+        K: PartialEq,
+        F: FnMut(&Update<O>) -> K,
+    {
+        let mut runs: Vec<(K, UnorderedBatch<O>)> = Vec::new();
+
+        for update in self.inner {
+            let update_key = key(&update);
+
+            match runs.last_mut() {
+                Some((run_key, run)) if *run_key == update_key => run.inner.push(update),
+                _ => runs.push((
+                    update_key,
+                    UnorderedBatch {
+                        inner: vec![update],
+                        max_parallelism: self.max_parallelism,
+                    },
+                )),
+            }
+        }
+
+        runs
+    }
+
+    /// Promotes this unordered batch into an ordered batch with the sequence number `seq_no`,
+    /// keeping the order of the updates. The resulting batch has no metadata.
+    pub fn into_ordered(self, seq_no: SeqNo) -> UpdateBatch<O> {
+        let mut batch = UpdateBatch::new(seq_no);
+
+        batch.inner = self.inner;
+
+        batch
+    }
+
     /// Returns the length of the batch.
     pub fn len(&self) -> usize {
         self.inner.len()
     }
+
+    /// Returns whether the batch holds no requests.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns an iterator over the updates in this batch.
+    pub fn iter(&self) -> impl Iterator<Item = &Update<O>> {
+        self.inner.iter()
+    }
+
+    /// Returns an iterator that allows modifying each update in this batch.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Update<O>> {
+        self.inner.iter_mut()
+    }
+
+    /// Retains only the updates for which `f` returns `true`, preserving their order.
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&Update<O>) -> bool,
+    {
+        self.inner.retain(f);
+    }
+
+    /// Removes later duplicates of an operation, identified by its `(from, session_id, operation_id)`,
+    /// keeping the first occurrence and the order of the remaining updates.
+    ///
+    /// This is never done automatically, so deterministic replay is preserved.
+    /// Returns the amount of updates that were removed.
+    pub fn dedup_by_operation(&mut self) -> usize {
+        dedup_updates(&mut self.inner)
+    }
+
+    /// The set of nodes that contributed requests to this batch, sorted by their id.
+    pub fn contributing_nodes(&self) -> BTreeSet<NodeId> {
+        self.inner.iter().map(Update::from).collect()
+    }
 }
 
-impl<O> Deref for BatchReplies<O> {
-    type Target = Vec<UpdateReply<O>>;
+/// A single request of an ordered batch, carrying enough context to be scheduled
+/// independently of the rest of the batch while still allowing its reply to be
+/// placed back at the right position.
+pub struct ExecUnit<O> {
+    seq_no: SeqNo,
+    index: usize,
+    update: Update<O>,
+}
 
-    fn deref(&self) -> &Self::Target {
-        &self.inner
+impl<O> ExecUnit<O> {
+    /// The sequence number of the batch this request belongs to.
+    pub fn sequence_number(&self) -> SeqNo {
+        self.seq_no
+    }
+
+    /// The position of this request within its batch.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn update(&self) -> &Update<O> {
+        &self.update
+    }
+
+    pub fn into_inner(self) -> (SeqNo, usize, Update<O>) {
+        (self.seq_no, self.index, self.update)
     }
 }
 
-impl<O> DerefMut for BatchReplies<O> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.inner
+/// Conversion of a batch into independently schedulable [`ExecUnit`]s.
+pub trait IntoExecUnits<O> {
+    type Units: Iterator<Item = ExecUnit<O>>;
+
+    /// Splits this batch into its execution units, in batch order.
+    fn into_exec_units(self) -> Self::Units;
+}
+
+/// Iterator over the execution units of an ordered batch (see [`IntoExecUnits`])
+pub struct ExecUnits<O> {
+    seq_no: SeqNo,
+    updates: std::iter::Enumerate<std::vec::IntoIter<Update<O>>>,
+}
+
+impl<O> Iterator for ExecUnits<O> {
+    type Item = ExecUnit<O>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.updates.next().map(|(index, update)| ExecUnit {
+            seq_no: self.seq_no,
+            index,
+            update,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.updates.size_hint()
     }
 }
 
-impl<O> From<Vec<UpdateReply<O>>> for BatchReplies<O> {
-    fn from(value: Vec<UpdateReply<O>>) -> Self {
-        Self { inner: value }
+impl<O> ExactSizeIterator for ExecUnits<O> {}
+
+impl<O> IntoExecUnits<O> for UpdateBatch<O> {
+    type Units = ExecUnits<O>;
+
+    fn into_exec_units(self) -> Self::Units {
+        ExecUnits {
+            seq_no: self.seq_no,
+            updates: self.inner.into_iter().enumerate(),
+        }
     }
 }
 
-impl<P> UpdateReply<P> {
-    pub fn init(to: NodeId, session_id: SeqNo, operation_id: SeqNo, payload: P) -> Self {
+fn dedup_updates<O>(updates: &mut Vec<Update<O>>) -> usize {
+    let before = updates.len();
+    let mut seen = HashSet::with_capacity(before);
+
+    updates.retain(|update| seen.insert(update.operation_key()));
+
+    before - updates.len()
+}
+
+impl<O> AsRef<[Update<O>]> for UpdateBatch<O> {
+    fn as_ref(&self) -> &[Update<O>] {
+        &self.inner[..]
+    }
+}
+
+impl<O> IntoIterator for UpdateBatch<O> {
+    type Item = Update<O>;
+    type IntoIter = std::vec::IntoIter<Update<O>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.into_iter()
+    }
+}
+
+impl<'a, O> IntoIterator for &'a UpdateBatch<O> {
+    type Item = &'a Update<O>;
+    type IntoIter = std::slice::Iter<'a, Update<O>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.iter()
+    }
+}
+
+impl<'a, O> IntoIterator for &'a mut UpdateBatch<O> {
+    type Item = &'a mut Update<O>;
+    type IntoIter = std::slice::IterMut<'a, Update<O>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.iter_mut()
+    }
+}
+
+impl<O> Update<O> {
+    pub fn new(from: NodeId, session_id: SeqNo, operation_id: SeqNo, operation: O) -> Self {
         Self {
-            to,
+            from,
             session_id,
             operation_id,
-            payload,
+            operation,
+            deadline: None,
+            depends_on: None,
         }
     }
 
-    pub fn to(&self) -> NodeId {
-        self.to
+    /// Returns the inner types stored in this `Update`.
+    pub fn into_inner(self) -> (NodeId, SeqNo, SeqNo, O) {
+        (
+            self.from,
+            self.session_id,
+            self.operation_id,
+            self.operation,
+        )
     }
 
-    /// Returns the inner types stored in this `UpdateReply`.
-    pub fn into_inner(self) -> (NodeId, SeqNo, SeqNo, P) {
-        (self.to, self.session_id, self.operation_id, self.payload)
+    /// Returns a reference to this operation in this `Update`.
+    pub fn operation(&self) -> &O {
+        &self.operation
+    }
+
+    pub fn from(&self) -> NodeId {
+        self.from
+    }
+
+    pub fn session_id(&self) -> SeqNo {
+        self.session_id
+    }
+
+    pub fn operation_id(&self) -> SeqNo {
+        self.operation_id
+    }
+
+    /// The key that uniquely identifies this operation: `(from, session_id, operation_id)`
+    pub fn operation_key(&self) -> (NodeId, SeqNo, SeqNo) {
+        (self.from, self.session_id, self.operation_id)
+    }
+
+    /// Transforms the operation of this update, preserving its routing fields.
+    pub fn map<U, F>(self, f: F) -> Update<U>
+    where
+        F: FnOnce(O) -> U,
+    {
+        Update {
+            from: self.from,
+            session_id: self.session_id,
+            operation_id: self.operation_id,
+            operation: f(self.operation),
+            deadline: self.deadline,
+            depends_on: self.depends_on,
+        }
+    }
+
+    /// A view of this update which borrows its operation, without cloning it.
+    pub fn as_ref_operation(&self) -> Update<&O> {
+        Update {
+            from: self.from,
+            session_id: self.session_id,
+            operation_id: self.operation_id,
+            operation: &self.operation,
+            deadline: self.deadline,
+            depends_on: self.depends_on,
+        }
+    }
+
+    /// Canonical ordering of updates, independent of their arrival order:
+    /// by `from`, then `session_id`, then `operation_id`.
+    pub fn cmp_canonical(&self, other: &Self) -> Ordering {
+        self.operation_key().cmp(&other.operation_key())
+    }
+
+    /// Sets the instant after which this update is no longer worth executing.
+    ///
+    /// The deadline is local to this replica, so it is only acted upon by the proposer,
+    /// before the update is ordered (see [`UpdateBatch::drop_expired()`]).
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets (or clears) the deadline of this update in place, e.g. while it is held by a batch.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Whether this update's deadline has passed at `now`.
+    /// Updates without a deadline never expire.
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.deadline.is_some_and(|deadline| deadline < now)
+    }
+
+    /// Marks this update as having to be executed after the batch with sequence number `seq`.
+    pub fn with_depends_on(mut self, seq: SeqNo) -> Self {
+        self.depends_on = Some(seq);
+        self
+    }
+
+    /// Sets (or clears) the dependency of this update in place, e.g. while it is held by a batch.
+    pub fn set_depends_on(&mut self, depends_on: Option<SeqNo>) {
+        self.depends_on = depends_on;
+    }
+
+    pub fn depends_on(&self) -> Option<SeqNo> {
+        self.depends_on
+    }
+}
+
+impl<P> BatchReplies<P> {
+    /*
+        /// Returns a new, empty batch of replies.
+        pub fn new() -> Self {
+            Self { inner: Vec::new() }
+        }
+    */
+
+    /// Returns a new, empty batch of replies, with the given capacity.
+    pub fn with_capacity(n: usize) -> Self {
+        Self {
+            inner: Vec::with_capacity(n),
+        }
+    }
+
+    /// Adds a new update reply to the batch.
+    pub fn add(&mut self, to: NodeId, session_id: SeqNo, operation_id: SeqNo, payload: P) {
+        self.inner.push(UpdateReply {
+            to,
+            session_id,
+            operation_id,
+            payload,
+        });
+    }
+
+    pub fn push(&mut self, reply: UpdateReply<P>) {
+        self.inner.push(reply);
+    }
+
+    /// Reserves capacity for at least `additional` more replies.
+    pub fn reserve(&mut self, additional: usize) {
+        self.inner.reserve(additional);
+    }
+
+    /// Appends all the replies of `other` to this batch, preserving their order.
+    pub fn extend_from(&mut self, other: BatchReplies<P>) {
+        self.inner.extend(other.inner);
+    }
+
+    /// Reassembles the replies of a batch that was executed in parallel chunks, which may
+    /// have completed in any order.
+    ///
+    /// Each chunk is tagged with the index, within the original batch, of its first request.
+    /// The chunks are concatenated in index order, so the result matches the order of the
+    /// requests before the batch was split. The chunks must cover the batch without gaps
+    /// or overlaps, which is checked in debug builds.
+    pub fn reassemble(mut chunks: Vec<(usize, BatchReplies<P>)>) -> BatchReplies<P> {
+        chunks.sort_by_key(|(start, _)| *start);
+
+        let mut reassembled =
+            BatchReplies::with_capacity(chunks.iter().map(|(_, chunk)| chunk.len()).sum());
+
+        for (start, chunk) in chunks {
+            debug_assert_eq!(
+                start,
+                reassembled.len(),
+                "Chunk starting at {} does not follow the {} replies reassembled so far",
+                start,
+                reassembled.len()
+            );
+
+            reassembled.extend_from(chunk);
+        }
+
+        reassembled
+    }
+
+    pub fn inner(&self) -> &Vec<UpdateReply<P>> {
+        &self.inner
+    }
+
+    /// Returns the inner storage.
+    pub fn into_inner(self) -> Vec<UpdateReply<P>> {
+        self.inner
+    }
+
+    /// Takes the inner storage, leaving this batch empty so it can be reused.
+    pub fn take_inner(&mut self) -> Vec<UpdateReply<P>> {
+        std::mem::take(&mut self.inner)
+    }
+
+    /// Returns the length of the batch.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns whether the batch holds no replies.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Sorts the replies by `(to, session_id, operation_id)`, instead of the order
+    /// in which they were produced. The sort is stable.
+    ///
+    /// Must only be used where the consumer does not depend on the execution order of the replies.
+    pub fn sort_by_destination(&mut self) {
+        self.inner
+            .sort_by_key(|reply| (reply.to, reply.session_id, reply.operation_id));
+    }
+
+    /// Transforms the payload of every reply in this batch, preserving their order
+    /// as well as the destination and ordering fields of each reply.
+    pub fn map_payloads<Q, F>(self, mut f: F) -> BatchReplies<Q>
+    where
+        F: FnMut(P) -> Q,
+    {
+        BatchReplies {
+            inner: self
+                .inner
+                .into_iter()
+                .map(|reply| reply.map(&mut f))
+                .collect(),
+        }
+    }
+
+    /// Summarizes this batch, for logging.
+    pub fn summary(&self) -> BatchSummary {
+        let distinct_nodes = self
+            .inner
+            .iter()
+            .map(UpdateReply::to)
+            .collect::<HashSet<_>>()
+            .len();
+
+        BatchSummary {
+            reply_count: self.inner.len(),
+            distinct_nodes,
+            payload_bytes: None,
+        }
+    }
+
+    /// Same as `summary()`, additionally reporting the total size of the reply payloads.
+    pub fn summary_with_size(&self) -> BatchSummary
+    where
+        P: SizeOf,
+    {
+        let payload_bytes = self.inner.iter().map(|reply| reply.payload.size_of()).sum();
+
+        BatchSummary {
+            payload_bytes: Some(payload_bytes),
+            ..self.summary()
+        }
+    }
+
+    /// Checks that, within each client session (identified by `(to, session_id)`),
+    /// the replies are in non decreasing operation id order, as clients expect them.
+    ///
+    /// This is a diagnostic meant to catch executors that shuffle replies, returning an
+    /// error identifying the first session found out of order.
+    pub fn assert_session_ordering(&self) -> Result<()> {
+        let mut last_operations: HashMap<(NodeId, SeqNo), SeqNo> = HashMap::new();
+
+        for reply in &self.inner {
+            let session = (reply.to, reply.session_id);
+
+            if let Some(previous) = last_operations.insert(session, reply.operation_id) {
+                if previous > reply.operation_id {
+                    return Err(BatchError::ReplyOrderViolation {
+                        to: reply.to,
+                        session: reply.session_id,
+                        previous,
+                        operation: reply.operation_id,
+                    }
+                    .into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns an iterator over the replies destined to `node`, in order.
+    pub fn replies_for(&self, node: NodeId) -> impl Iterator<Item = &UpdateReply<P>> {
+        self.inner.iter().filter(move |reply| reply.to == node)
+    }
+
+    /// Groups the replies by their destination, preserving the order of the replies
+    /// destined to each node.
+    pub fn group_by_node(self) -> HashMap<NodeId, Vec<UpdateReply<P>>> {
+        let mut grouped: HashMap<NodeId, Vec<UpdateReply<P>>> = HashMap::new();
+
+        for reply in self.inner {
+            grouped.entry(reply.to).or_default().push(reply);
+        }
+
+        grouped
+    }
+
+    /// Coalesces the replies destined to the same node into a single entry, ready to be
+    /// sent as one message.
+    ///
+    /// Nodes are ordered by their first appearance in the batch and the replies of each
+    /// node keep their relative order. Each reply is flattened into its session id,
+    /// operation id and payload.
+    pub fn into_node_batches(self) -> Vec<NodeReplyBatch<P>> {
+        let mut positions: HashMap<NodeId, usize> = HashMap::new();
+        let mut batches: Vec<NodeReplyBatch<P>> = Vec::new();
+
+        for reply in self.inner {
+            let (to, session_id, operation_id, payload) = reply.into_inner();
+
+            let position = *positions.entry(to).or_insert_with(|| {
+                batches.push((to, Vec::new()));
+
+                batches.len() - 1
+            });
+
+            batches[position]
+                .1
+                .push((session_id, operation_id, payload));
+        }
+
+        batches
+    }
+}
+
+/// The replies destined to a single node, as produced by [`BatchReplies::into_node_batches`].
+/// Each reply is represented by its session id, operation id and payload.
+pub type NodeReplyBatch<P> = (NodeId, Vec<(SeqNo, SeqNo, P)>);
+
+/// A summary of a batch of replies, meant for logging
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchSummary {
+    reply_count: usize,
+    distinct_nodes: usize,
+    payload_bytes: Option<usize>,
+}
+
+impl BatchSummary {
+    pub fn reply_count(&self) -> usize {
+        self.reply_count
+    }
+
+    /// The amount of distinct nodes the replies are destined to
+    pub fn distinct_nodes(&self) -> usize {
+        self.distinct_nodes
+    }
+
+    /// The total size of the reply payloads, if it was computed
+    pub fn payload_bytes(&self) -> Option<usize> {
+        self.payload_bytes
+    }
+}
+
+impl std::fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} replies to {} nodes",
+            self.reply_count, self.distinct_nodes
+        )?;
+
+        if let Some(payload_bytes) = self.payload_bytes {
+            write!(f, " ({} payload bytes)", payload_bytes)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A pool of reply batch allocations, so the backing storage of [`BatchReplies`]
+/// can be reused across batches instead of being allocated for every batch.
+pub struct ReplyPool<P> {
+    max_pooled: usize,
+    pooled: Mutex<Vec<Vec<UpdateReply<P>>>>,
+}
+
+impl<P> ReplyPool<P> {
+    /// Creates a pool which holds on to at most `max_pooled` allocations.
+    pub fn new(max_pooled: usize) -> Self {
+        Self {
+            max_pooled,
+            pooled: Mutex::new(Vec::with_capacity(max_pooled)),
+        }
+    }
+
+    /// Returns an empty batch of replies with at least the given capacity,
+    /// reusing a pooled allocation when available.
+    pub fn get(&self, capacity: usize) -> BatchReplies<P> {
+        let pooled = self
+            .pooled
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop();
+
+        match pooled {
+            Some(mut inner) => {
+                inner.reserve(capacity);
+
+                BatchReplies { inner }
+            }
+            None => BatchReplies::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the allocation of `replies` to the pool, clearing its contents.
+    pub fn recycle(&self, replies: BatchReplies<P>) {
+        let mut inner = replies.inner;
+
+        inner.clear();
+
+        let mut pooled = self.pooled.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if pooled.len() < self.max_pooled {
+            pooled.push(inner);
+        }
+    }
+}
+
+impl<P> BatchReplies<ReplyOutcome<P>> {
+    /// Adds the outcome of a request to the batch.
+    pub fn add_result(
+        &mut self,
+        to: NodeId,
+        session_id: SeqNo,
+        operation_id: SeqNo,
+        outcome: ReplyOutcome<P>,
+    ) {
+        self.add(to, session_id, operation_id, outcome);
+    }
+
+    /// Adds a successful reply to the batch.
+    pub fn add_ok(&mut self, to: NodeId, session_id: SeqNo, operation_id: SeqNo, payload: P) {
+        self.add_result(to, session_id, operation_id, ReplyOutcome::Ok(payload));
+    }
+
+    /// Adds an error reply to the batch.
+    pub fn add_err(
+        &mut self,
+        to: NodeId,
+        session_id: SeqNo,
+        operation_id: SeqNo,
+        error: ExecutionError,
+    ) {
+        self.add_result(to, session_id, operation_id, ReplyOutcome::Err(error));
+    }
+
+    /// Returns an iterator over the error replies in this batch, in order.
+    pub fn errors(&self) -> impl Iterator<Item = &UpdateReply<ReplyOutcome<P>>> {
+        self.inner
+            .iter()
+            .filter(|reply| matches!(reply.payload, ReplyOutcome::Err(_)))
+    }
+}
+
+impl<P> ReplyOutcome<P> {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, ReplyOutcome::Ok(_))
+    }
+
+    pub fn is_err(&self) -> bool {
+        matches!(self, ReplyOutcome::Err(_))
+    }
+
+    pub fn into_result(self) -> std::result::Result<P, ExecutionError> {
+        match self {
+            ReplyOutcome::Ok(payload) => Ok(payload),
+            ReplyOutcome::Err(error) => Err(error),
+        }
+    }
+}
+
+impl<P> From<std::result::Result<P, ExecutionError>> for ReplyOutcome<P> {
+    fn from(value: std::result::Result<P, ExecutionError>) -> Self {
+        match value {
+            Ok(payload) => ReplyOutcome::Ok(payload),
+            Err(error) => ReplyOutcome::Err(error),
+        }
+    }
+}
+
+/// Errors which are (or wrap) an [`ExecutionError`] are kept as is, while any other
+/// error is reported as [`ExecutionError::Rejected`], carrying its message.
+impl<P> From<Result<P>> for ReplyOutcome<P> {
+    fn from(value: Result<P>) -> Self {
+        match value {
+            Ok(payload) => ReplyOutcome::Ok(payload),
+            Err(error) => ReplyOutcome::Err(
+                error
+                    .downcast_ref::<ExecutionError>()
+                    .cloned()
+                    .unwrap_or_else(|| ExecutionError::Rejected(error.to_string())),
+            ),
+        }
+    }
+}
+
+/// How many characters of a payload are printed by the `Debug` implementations, as
+/// operations and replies may be arbitrarily large.
+const DEBUG_PAYLOAD_CHARS: usize = 64;
+
+/// How many entries of a batch are printed by the `Debug` implementations.
+const DEBUG_ENTRIES: usize = 8;
+
+/// Formats a payload, truncated to [`DEBUG_PAYLOAD_CHARS`].
+struct ElidedPayload<'a, T>(&'a T);
+
+impl<T> Debug for ElidedPayload<'_, T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let formatted = format!("{:?}", self.0);
+
+        match formatted.char_indices().nth(DEBUG_PAYLOAD_CHARS) {
+            Some((end, _)) => write!(f, "{}...", &formatted[..end]),
+            None => f.write_str(&formatted),
+        }
+    }
+}
+
+/// Formats the first [`DEBUG_ENTRIES`] entries of a list, followed by how many were left out.
+struct ElidedEntries<'a, T>(&'a [T]);
+
+impl<T> Debug for ElidedEntries<'_, T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut list = f.debug_list();
+
+        list.entries(self.0.iter().take(DEBUG_ENTRIES));
+
+        if self.0.len() > DEBUG_ENTRIES {
+            list.entry(&format_args!("... {} more", self.0.len() - DEBUG_ENTRIES));
+        }
+
+        list.finish()
+    }
+}
+
+impl<O> Debug for Update<O>
+where
+    O: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Update")
+            .field("from", &self.from)
+            .field("session_id", &self.session_id)
+            .field("operation_id", &self.operation_id)
+            .field("operation", &ElidedPayload(&self.operation))
+            .field("depends_on", &self.depends_on)
+            .finish()
+    }
+}
+
+impl<P> Debug for UpdateReply<P>
+where
+    P: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UpdateReply")
+            .field("to", &self.to)
+            .field("session_id", &self.session_id)
+            .field("operation_id", &self.operation_id)
+            .field("payload", &ElidedPayload(&self.payload))
+            .finish()
+    }
+}
+
+impl<O> Debug for UpdateBatch<O>
+where
+    O: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UpdateBatch")
+            .field("seq_no", &self.seq_no)
+            .field("priority", &self.priority)
+            .field("epoch", &self.epoch)
+            .field("has_meta", &self.meta.is_some())
+            .field("updates", &ElidedEntries(&self.inner))
+            .finish()
+    }
+}
+
+impl<O> IntoIterator for UnorderedBatch<O> {
+    type Item = Update<O>;
+    type IntoIter = std::vec::IntoIter<Update<O>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.into_iter()
+    }
+}
+
+impl<'a, O> IntoIterator for &'a UnorderedBatch<O> {
+    type Item = &'a Update<O>;
+    type IntoIter = std::slice::Iter<'a, Update<O>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.iter()
+    }
+}
+
+impl<'a, O> IntoIterator for &'a mut UnorderedBatch<O> {
+    type Item = &'a mut Update<O>;
+    type IntoIter = std::slice::IterMut<'a, Update<O>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.iter_mut()
+    }
+}
+
+impl<O> Debug for UnorderedBatch<O>
+where
+    O: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnorderedBatch")
+            .field("updates", &ElidedEntries(&self.inner))
+            .finish()
+    }
+}
+
+impl<P> Debug for BatchReplies<P>
+where
+    P: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchReplies")
+            .field("replies", &ElidedEntries(&self.inner))
+            .finish()
+    }
+}
+
+impl<O> Deref for BatchReplies<O> {
+    type Target = Vec<UpdateReply<O>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<O> DerefMut for BatchReplies<O> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<O> From<Vec<UpdateReply<O>>> for BatchReplies<O> {
+    fn from(value: Vec<UpdateReply<O>>) -> Self {
+        Self { inner: value }
+    }
+}
+
+impl<P> UpdateReply<P> {
+    pub fn init(to: NodeId, session_id: SeqNo, operation_id: SeqNo, payload: P) -> Self {
+        Self {
+            to,
+            session_id,
+            operation_id,
+            payload,
+        }
+    }
+
+    pub fn to(&self) -> NodeId {
+        self.to
+    }
+
+    /// Transforms the payload of this reply, preserving its destination and ordering fields.
+    pub fn map<Q, F>(self, f: F) -> UpdateReply<Q>
+    where
+        F: FnOnce(P) -> Q,
+    {
+        UpdateReply {
+            to: self.to,
+            session_id: self.session_id,
+            operation_id: self.operation_id,
+            payload: f(self.payload),
+        }
+    }
+
+    /// Returns the inner types stored in this `UpdateReply`.
+    pub fn into_inner(self) -> (NodeId, SeqNo, SeqNo, P) {
+        (self.to, self.session_id, self.operation_id, self.payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+    use std::time::{Duration, Instant};
+
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+
+    use super::*;
+
+    /// Requests and replies are plain `u64`s
+    struct TestData;
+
+    impl ApplicationData for TestData {
+        type Request = u64;
+        type Reply = u64;
+
+        fn serialize_request<W>(mut w: W, request: &Self::Request) -> Result<()>
+        where
+            W: Write,
+        {
+            Ok(w.write_all(&request.to_le_bytes())?)
+        }
+
+        fn deserialize_request<R>(mut r: R) -> Result<Self::Request>
+        where
+            R: Read,
+        {
+            let mut bytes = [0; 8];
+            r.read_exact(&mut bytes)?;
+
+            Ok(u64::from_le_bytes(bytes))
+        }
+
+        fn serialize_reply<W>(w: W, reply: &Self::Reply) -> Result<()>
+        where
+            W: Write,
+        {
+            Self::serialize_request(w, reply)
+        }
+
+        fn deserialize_reply<R>(r: R) -> Result<Self::Reply>
+        where
+            R: Read,
+        {
+            Self::deserialize_request(r)
+        }
+    }
+
+    /// Requests with this value are rejected by [`SumApp::try_update()`]
+    const REJECTED: u64 = u64::MAX;
+
+    /// Adds every request to a `u64` state, replying with the new sum
+    #[derive(Default)]
+    struct SumApp {
+        pre_batches: AtomicUsize,
+        post_batches: AtomicUsize,
+    }
+
+    impl Application<u64> for SumApp {
+        type AppData = TestData;
+
+        fn initial_state() -> Result<u64> {
+            Ok(0)
+        }
+
+        fn unordered_execution(&self, state: &u64, request: u64) -> u64 {
+            *state + request
+        }
+
+        fn update(&self, state: &mut u64, request: u64) -> u64 {
+            *state += request;
+
+            *state
+        }
+
+        fn read(&self, state: &u64, _from: NodeId) -> Option<u64> {
+            Some(*state)
+        }
+
+        fn pre_batch(&self, _state: &mut u64, _seq: SeqNo) {
+            self.pre_batches.fetch_add(1, AtomicOrdering::Relaxed);
+        }
+
+        fn post_batch(&self, _state: &mut u64, _seq: SeqNo) {
+            self.post_batches.fetch_add(1, AtomicOrdering::Relaxed);
+        }
+
+        fn try_update(&self, state: &mut u64, request: u64) -> Result<u64> {
+            if request == REJECTED {
+                return Err(anyhow::anyhow!("Request {} is not allowed", request));
+            }
+
+            Ok(self.update(state, request))
+        }
+    }
+
+    fn update(op: u64) -> Update<u64> {
+        Update::new(NodeId(0), SeqNo::ZERO, SeqNo::from(op as u32), op)
+    }
+
+    #[test]
+    fn drop_expired_removes_only_expired_updates() {
+        let now = Instant::now();
+        let mut batch = UpdateBatch::new(SeqNo::ZERO);
+
+        batch.push(update(0).with_deadline(now - Duration::from_secs(1)));
+        batch.push(update(1).with_deadline(now + Duration::from_secs(1)));
+        batch.add(NodeId(0), SeqNo::ZERO, SeqNo::from(2), 2);
+        batch.add(NodeId(0), SeqNo::ZERO, SeqNo::from(3), 3);
+
+        for update in batch.iter_mut().filter(|update| *update.operation() == 3) {
+            update.set_deadline(Some(now - Duration::from_millis(1)));
+        }
+
+        assert_eq!(batch.drop_expired(now), 2);
+
+        let remaining: Vec<_> = batch.iter().map(|update| *update.operation()).collect();
+        assert_eq!(remaining, vec![1, 2]);
+    }
+
+    #[test]
+    fn priority_defaults_to_normal_and_is_kept() {
+        let batch = UpdateBatch::<u64>::new(SeqNo::ZERO);
+
+        assert_eq!(batch.priority(), BatchPriority::Normal);
+
+        let mut batch = batch.with_priority(BatchPriority::High);
+
+        for op in 0..4 {
+            batch.push(update(op));
+        }
+
+        assert!(batch
+            .clone()
+            .split_into_chunks(2)
+            .iter()
+            .all(|chunk| chunk.priority() == BatchPriority::High));
+
+        let (even, odd) = batch.partition(|update| *update.operation() % 2 == 0);
+
+        assert_eq!(even.priority(), BatchPriority::High);
+        assert_eq!(odd.priority(), BatchPriority::High);
+    }
+
+    fn batch_of(seq: u32, len: u64) -> UpdateBatch<u64> {
+        let mut batch = UpdateBatch::new(SeqNo::from(seq));
+
+        for op in 0..len {
+            batch.push(update(op));
+        }
+
+        batch
+    }
+
+    fn operations<'a>(updates: impl IntoIterator<Item = &'a Update<u64>>) -> Vec<u64> {
+        updates
+            .into_iter()
+            .map(|update| *update.operation())
+            .collect()
+    }
+
+    #[test]
+    fn iterating_does_not_consume_the_batch() {
+        let mut batch = batch_of(3, 3);
+
+        assert_eq!(operations(batch.iter()), vec![0, 1, 2]);
+        assert_eq!(operations(&batch), vec![0, 1, 2]);
+
+        for update in &mut batch {
+            update.set_deadline(Some(Instant::now()));
+        }
+
+        assert!(batch.iter().all(|update| update.deadline().is_some()));
+        assert_eq!(batch.as_ref().len(), 3);
+
+        let owned: Vec<_> = batch
+            .into_iter()
+            .map(|update| update.into_inner().3)
+            .collect();
+
+        assert_eq!(owned, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn retain_keeps_the_sequence_number() {
+        let mut batch = batch_of(7, 5);
+
+        batch.retain(|update| *update.operation() % 2 == 0);
+
+        assert_eq!(batch.sequence_number(), SeqNo::from(7));
+        assert_eq!(operations(&batch), vec![0, 2, 4]);
+
+        batch.retain(|_| false);
+
+        assert!(batch.is_empty());
+        assert_eq!(batch.sequence_number(), SeqNo::from(7));
+    }
+
+    #[test]
+    fn merge_appends_in_order_and_takes_the_later_seq() {
+        let mut first = batch_of(1, 2);
+        let mut second = UpdateBatch::new(SeqNo::from(2));
+
+        second.push(update(10));
+        second.push(update(11));
+
+        first.merge(second).unwrap();
+
+        assert_eq!(first.sequence_number(), SeqNo::from(2));
+        assert_eq!(operations(&first), vec![0, 1, 10, 11]);
+    }
+
+    #[test]
+    fn merge_rejects_preceding_batches() {
+        let mut later = batch_of(5, 1);
+
+        let err = later.merge(batch_of(4, 1)).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<BatchError>(),
+            Some(BatchError::MergeOutOfOrder { .. })
+        ));
+        assert_eq!(later.len(), 1);
+        assert_eq!(later.sequence_number(), SeqNo::from(5));
+    }
+
+    #[test]
+    fn split_into_chunks_keeps_order_and_seq() {
+        let mut batch = batch_of(9, 1000);
+
+        batch.append_batch_meta(BatchMeta::new());
+
+        let mut chunks = batch.split_into_chunks(256);
+
+        let sizes: Vec<_> = chunks.iter().map(UpdateBatch::len).collect();
+        assert_eq!(sizes, vec![256, 256, 256, 232]);
+
+        assert!(chunks
+            .iter()
+            .all(|chunk| chunk.sequence_number() == SeqNo::from(9)));
+
+        assert!(chunks[0].take_meta().is_some());
+        assert!(chunks[1..]
+            .iter_mut()
+            .all(|chunk| chunk.take_meta().is_none()));
+
+        let rejoined: Vec<_> = chunks
+            .into_iter()
+            .flat_map(UpdateBatch::into_inner)
+            .map(|update| *update.operation())
+            .collect();
+
+        assert_eq!(rejoined, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn dedup_by_operation_is_stable() {
+        let mut unique = batch_of(0, 4);
+
+        assert_eq!(unique.dedup_by_operation(), 0);
+        assert_eq!(operations(&unique), vec![0, 1, 2, 3]);
+
+        let mut batch = UpdateBatch::new(SeqNo::ZERO);
+
+        for op in [2, 0, 2, 1, 0, 3] {
+            batch.push(update(op));
+        }
+
+        assert_eq!(batch.dedup_by_operation(), 2);
+        assert_eq!(operations(&batch), vec![2, 0, 1, 3]);
+    }
+
+    fn replies_to(destinations: &[u32]) -> BatchReplies<u64> {
+        let mut replies = BatchReplies::with_capacity(destinations.len());
+
+        for (op, to) in destinations.iter().enumerate() {
+            replies.add(NodeId(*to), SeqNo::ZERO, SeqNo::from(op as u32), op as u64);
+        }
+
+        replies
+    }
+
+    #[test]
+    fn group_by_node_keeps_per_node_order() {
+        let mut grouped = replies_to(&[1, 2, 1, 3, 2, 1]).group_by_node();
+
+        assert_eq!(grouped.len(), 3);
+        assert!(!grouped.contains_key(&NodeId(0)));
+
+        let mut payloads = |node: u32| -> Vec<u64> {
+            grouped
+                .remove(&NodeId(node))
+                .unwrap()
+                .into_iter()
+                .map(|reply| reply.into_inner().3)
+                .collect()
+        };
+
+        assert_eq!(payloads(1), vec![0, 2, 5]);
+        assert_eq!(payloads(2), vec![1, 4]);
+        assert_eq!(payloads(3), vec![3]);
+    }
+
+    #[test]
+    fn try_update_batch_collects_each_result() {
+        let app = SumApp::default();
+        let mut state = 0;
+
+        let mut batch = UpdateBatch::new(SeqNo::ZERO);
+        batch.push(update(2));
+        batch.push(Update::new(NodeId(1), SeqNo::ZERO, SeqNo::ZERO, REJECTED));
+        batch.push(update(3));
+
+        let results: Vec<_> = app
+            .try_update_batch(&mut state, batch)
+            .into_inner()
+            .into_iter()
+            .map(|reply| reply.into_inner().3.into_result().ok())
+            .collect();
+
+        assert_eq!(results, vec![Some(2), None, Some(5)]);
+        assert_eq!(state, 5);
+    }
+
+    #[test]
+    fn batch_hooks_run_once_per_batch() {
+        let app = SumApp::default();
+        let mut state = 0;
+
+        app.update_batch(&mut state, batch_of(0, 3));
+        app.update_batch(&mut state, batch_of(1, 0));
+        app.try_update_batch(&mut state, batch_of(2, 2));
+
+        assert_eq!(app.pre_batches.load(AtomicOrdering::Relaxed), 3);
+        assert_eq!(app.post_batches.load(AtomicOrdering::Relaxed), 3);
+    }
+
+    #[test]
+    fn reads_are_served_from_the_committed_state() {
+        let app = SumApp::default();
+        let mut state = 0;
+
+        app.update_batch(&mut state, batch_of(0, 4));
+
+        assert_eq!(app.read(&state, NodeId(3)), Some(6));
+        assert_eq!(app.read_query(&state, NodeId(3), None), Some(6));
+    }
+
+    const KEYS: usize = 4;
+
+    /// Counts, per key, how many times it was updated.
+    /// Replies do not depend on the state, so execution order does not matter.
+    struct CounterPerKeyApp;
+
+    impl Application<Vec<AtomicUsize>> for CounterPerKeyApp {
+        type AppData = TestData;
+
+        fn initial_state() -> Result<Vec<AtomicUsize>> {
+            Ok((0..KEYS).map(|_| AtomicUsize::new(0)).collect())
+        }
+
+        fn unordered_execution(&self, state: &Vec<AtomicUsize>, request: u64) -> u64 {
+            state[request as usize % KEYS].load(AtomicOrdering::Relaxed) as u64
+        }
+
+        fn update(&self, state: &mut Vec<AtomicUsize>, request: u64) -> u64 {
+            self.update_commutative(state, request)
+        }
+
+        fn is_commutative(&self) -> bool {
+            true
+        }
+    }
+
+    impl CommutativeApplication<Vec<AtomicUsize>> for CounterPerKeyApp {
+        fn update_commutative(&self, state: &Vec<AtomicUsize>, request: u64) -> u64 {
+            state[request as usize % KEYS].fetch_add(1, AtomicOrdering::Relaxed);
+
+            request * 2
+        }
+    }
+
+    fn counts(state: &[AtomicUsize]) -> Vec<usize> {
+        state
+            .iter()
+            .map(|count| count.load(AtomicOrdering::Relaxed))
+            .collect()
+    }
+
+    #[test]
+    fn parallel_execution_matches_the_serial_path() {
+        let app = CounterPerKeyApp;
+
+        let mut serial_state = CounterPerKeyApp::initial_state().unwrap();
+        let mut parallel_state = CounterPerKeyApp::initial_state().unwrap();
+
+        let serial = app.update_batch(&mut serial_state, batch_of(0, 101));
+        let parallel = app.execute_batch_parallel(&mut parallel_state, batch_of(0, 101));
+
+        let routing = |replies: BatchReplies<u64>| -> Vec<_> {
+            replies
+                .into_inner()
+                .into_iter()
+                .map(UpdateReply::into_inner)
+                .collect()
+        };
+
+        assert_eq!(routing(serial), routing(parallel));
+        assert_eq!(counts(&serial_state), counts(&parallel_state));
+        assert_eq!(counts(&parallel_state), vec![26, 25, 25, 25]);
+    }
+
+    #[test]
+    fn extend_from_preserves_order_and_reserve_keeps_len() {
+        let mut replies = replies_to(&[1, 2]);
+
+        replies.reserve(16);
+
+        assert_eq!(replies.len(), 2);
+        assert!(replies.inner().capacity() >= 18);
+
+        replies.extend_from(replies_to(&[3, 4, 5]));
+
+        let destinations: Vec<_> = replies.iter().map(UpdateReply::to).collect();
+
+        assert_eq!(
+            destinations,
+            vec![NodeId(1), NodeId(2), NodeId(3), NodeId(4), NodeId(5)]
+        );
+    }
+
+    #[cfg(feature = "serialize_serde")]
+    #[test]
+    fn batch_round_trips_through_json() {
+        let mut batch = UpdateBatch::new(SeqNo::from(12u32))
+            .with_priority(BatchPriority::High)
+            .with_epoch(3)
+            .with_trace_context(vec![9, 9]);
+
+        batch.add(
+            NodeId(1),
+            SeqNo::from(2u32),
+            SeqNo::from(3u32),
+            vec![1u8, 2, 3],
+        );
+        batch.push(
+            Update::new(NodeId(4), SeqNo::from(5u32), SeqNo::from(6u32), vec![])
+                .with_depends_on(SeqNo::from(11u32)),
+        );
+
+        let json = serde_json::to_string(&batch).unwrap();
+        let decoded: UpdateBatch<Vec<u8>> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded.sequence_number(), SeqNo::from(12u32));
+        assert_eq!(decoded.priority(), BatchPriority::High);
+        assert_eq!(decoded.epoch(), Some(3));
+        assert_eq!(decoded.trace_context(), Some(&[9u8, 9][..]));
+        assert_eq!(decoded.len(), batch.len());
+
+        for (decoded, original) in decoded.iter().zip(batch.iter()) {
+            assert_eq!(decoded.from(), original.from());
+            assert_eq!(decoded.session_id(), original.session_id());
+            assert_eq!(decoded.operation_id(), original.operation_id());
+            assert_eq!(decoded.operation(), original.operation());
+            assert_eq!(decoded.depends_on(), original.depends_on());
+        }
+
+        // The identifiers must come back as the very same values, not just equal in order
+        let first = decoded.iter().next().unwrap();
+
+        assert_eq!(first.from(), NodeId(1));
+        assert_eq!(first.session_id(), SeqNo::from(2u32));
+        assert_eq!(first.operation_id(), SeqNo::from(3u32));
+    }
+
+    #[cfg(feature = "serialize_serde")]
+    #[test]
+    fn replies_round_trip_through_json() {
+        let mut replies = BatchReplies::with_capacity(2);
+
+        replies.add(NodeId(3), SeqNo::from(1u32), SeqNo::from(8u32), vec![7u8]);
+        replies.add(NodeId(5), SeqNo::ZERO, SeqNo::ONE, vec![]);
+
+        let json = serde_json::to_string(&replies).unwrap();
+        let decoded: BatchReplies<Vec<u8>> = serde_json::from_str(&json).unwrap();
+
+        let fields = |replies: BatchReplies<Vec<u8>>| -> Vec<_> {
+            replies
+                .into_inner()
+                .into_iter()
+                .map(UpdateReply::into_inner)
+                .collect()
+        };
+
+        assert_eq!(fields(decoded), fields(replies));
+    }
+
+    #[test]
+    fn sort_canonical_is_deterministic() {
+        let keys = [
+            (2, 0, 1),
+            (0, 1, 0),
+            (2, 0, 0),
+            (0, 0, 5),
+            (1, 3, 2),
+            (0, 0, 1),
+        ];
+
+        let shuffled = |order: &[usize]| {
+            let mut batch = UpdateBatch::new(SeqNo::ZERO);
+
+            for index in order {
+                let (from, session, op) = keys[*index];
+
+                batch.add(NodeId(from), SeqNo::from(session), SeqNo::from(op), 0u64);
+            }
+
+            batch.sort_canonical();
+
+            batch.iter().map(Update::operation_key).collect::<Vec<_>>()
+        };
+
+        let sorted = shuffled(&[0, 1, 2, 3, 4, 5]);
+
+        assert_eq!(sorted, shuffled(&[5, 3, 1, 4, 0, 2]));
+        assert_eq!(
+            sorted[..3],
+            [
+                (NodeId(0), SeqNo::ZERO, SeqNo::from(1)),
+                (NodeId(0), SeqNo::ZERO, SeqNo::from(5)),
+                (NodeId(0), SeqNo::ONE, SeqNo::ZERO),
+            ]
+        );
+        assert_eq!(sorted[5], (NodeId(2), SeqNo::ZERO, SeqNo::ONE));
+    }
+
+    #[test]
+    fn proposed_at_survives_reconstruction() {
+        assert_eq!(batch_of(0, 2).proposed_at(), None);
+
+        let proposed_at = Instant::now();
+        let batch = batch_of(4, 2).with_proposed_at(proposed_at);
+
+        let rebuilt = batch
+            .map_operations(|op| op + 1)
+            .split_into_chunks(1)
+            .remove(1);
+
+        assert_eq!(rebuilt.proposed_at(), Some(proposed_at));
+
+        let mut reassembled =
+            UpdateBatch::new(rebuilt.sequence_number()).with_proposed_at(proposed_at);
+
+        for update in rebuilt.into_inner() {
+            reassembled.push(update);
+        }
+
+        assert_eq!(reassembled.proposed_at(), Some(proposed_at));
+        assert_eq!(operations(&reassembled), vec![2]);
+    }
+
+    #[test]
+    fn with_meta_mut_initializes_missing_meta() {
+        let mut batch = batch_of(0, 1);
+
+        assert!(batch.take_meta().is_none());
+
+        let _ = batch.with_meta_mut();
+
+        assert!(batch.take_meta().is_some());
+        assert!(batch.take_meta().is_none());
+    }
+
+    #[test]
+    fn catch_up_matches_per_batch_execution() {
+        let app = SumApp::default();
+
+        let mut per_batch = 0;
+        for seq in 0..3 {
+            app.update_batch(&mut per_batch, batch_of(seq, 4));
+        }
+
+        let mut caught_up = 0;
+        let replies = app.update_catch_up(
+            &mut caught_up,
+            MaybeVec::from_many((0..3).map(|seq| batch_of(seq, 4)).collect()),
+        );
+
+        assert_eq!(caught_up, per_batch);
+        assert_eq!(replies.len(), 3);
+    }
+
+    #[test]
+    fn max_parallelism_is_only_a_hint() {
+        let app = SumApp::default();
+
+        let mut requests = UnorderedBatch::new().with_max_parallelism(2);
+        for op in 0..5 {
+            requests.add(NodeId(0), SeqNo::ZERO, SeqNo::from(op), u64::from(op));
+        }
+
+        assert_eq!(requests.max_parallelism(), Some(2));
+        assert_eq!(UnorderedBatch::<u64>::new().max_parallelism(), None);
+
+        let replies: Vec<_> = app
+            .unordered_batched_execution(&10, requests)
+            .into_inner()
+            .into_iter()
+            .map(|reply| reply.into_inner().3)
+            .collect();
+
+        assert_eq!(replies, vec![10, 11, 12, 13, 14]);
+    }
+
+    #[test]
+    fn map_keeps_the_routing_fields() {
+        let update = Update::new(NodeId(3), SeqNo::from(4), SeqNo::from(5), 6u64);
+
+        assert_eq!(
+            update.map(|op| op.to_string()).into_inner(),
+            (NodeId(3), SeqNo::from(4), SeqNo::from(5), String::from("6"))
+        );
+
+        let mut batch = batch_of(8, 3);
+        batch.append_batch_meta(BatchMeta::new());
+
+        let keys: Vec<_> = batch.iter().map(Update::operation_key).collect();
+        let mut mapped = batch.map_operations(|op| op * 10);
+
+        assert_eq!(mapped.sequence_number(), SeqNo::from(8));
+        assert!(mapped.take_meta().is_some());
+        assert_eq!(
+            mapped.iter().map(Update::operation_key).collect::<Vec<_>>(),
+            keys
+        );
+        assert_eq!(operations(&mapped), vec![0, 10, 20]);
+    }
+
+    #[test]
+    fn try_map_operations_reports_the_failing_index() {
+        let mapped = batch_of(2, 3).try_map_operations(u8::try_from).unwrap();
+
+        assert_eq!(mapped.sequence_number(), SeqNo::from(2));
+        assert_eq!(mapped.len(), 3);
+
+        let mut batch = batch_of(2, 4);
+        batch.push(update(1000));
+        batch.push(update(2000));
+
+        let err = batch.try_map_operations(u8::try_from).unwrap_err();
+
+        assert_eq!(err.index(), 4);
+    }
+
+    #[test]
+    fn no_reply_execution_changes_state_identically() {
+        let app = SumApp::default();
+
+        let mut with_replies = 0;
+        let mut without_replies = 0;
+
+        app.update_batch(&mut with_replies, batch_of(0, 6));
+        app.update_batch_no_reply(&mut without_replies, batch_of(0, 6));
+
+        assert_eq!(with_replies, 15);
+        assert_eq!(without_replies, with_replies);
+    }
+
+    #[test]
+    fn update_round_trips_through_into_inner() {
+        let update = Update::new(NodeId(1), SeqNo::from(2), SeqNo::from(3), vec![4u8]);
+
+        assert_eq!(
+            update.into_inner(),
+            (NodeId(1), SeqNo::from(2), SeqNo::from(3), vec![4u8])
+        );
+    }
+
+    #[test]
+    fn take_inner_leaves_a_reusable_batch() {
+        let mut batch = batch_of(6, 3);
+
+        assert_eq!(batch.take_inner().len(), 3);
+        assert!(batch.is_empty());
+        assert_eq!(batch.sequence_number(), SeqNo::from(6));
+
+        let mut unordered = UnorderedBatch::new();
+        unordered.add(NodeId(0), SeqNo::ZERO, SeqNo::ZERO, 1u64);
+
+        assert_eq!(unordered.take_inner().len(), 1);
+        assert!(unordered.is_empty());
+
+        let mut replies = replies_to(&[1, 2]);
+
+        assert_eq!(replies.take_inner().len(), 2);
+        assert!(replies.is_empty());
+    }
+
+    fn bytes_batch(operations: &[&[u8]]) -> UpdateBatch<Vec<u8>> {
+        let mut batch = UpdateBatch::new(SeqNo::from(1));
+
+        for (op, operation) in operations.iter().enumerate() {
+            batch.add(
+                NodeId(0),
+                SeqNo::ZERO,
+                SeqNo::from(op as u32),
+                operation.to_vec(),
+            );
+        }
+
+        batch
+    }
+
+    #[test]
+    fn fingerprint_is_order_sensitive() {
+        let batch = bytes_batch(&[b"a", b"bc"]);
+
+        assert_eq!(
+            batch.fingerprint(),
+            bytes_batch(&[b"a", b"bc"]).fingerprint()
+        );
+        assert_ne!(
+            batch.fingerprint(),
+            bytes_batch(&[b"bc", b"a"]).fingerprint()
+        );
+        assert_ne!(
+            batch.fingerprint(),
+            bytes_batch(&[b"ab", b"c"]).fingerprint()
+        );
+    }
+
+    #[test]
+    fn mixed_outcomes_are_told_apart() {
+        let mut replies = BatchReplies::with_capacity(3);
+
+        replies.add_ok(NodeId(0), SeqNo::ZERO, SeqNo::ZERO, 1u64);
+        replies.add_err(
+            NodeId(1),
+            SeqNo::ZERO,
+            SeqNo::ONE,
+            ExecutionError::Malformed(String::from("bad")),
+        );
+        replies.add_result(NodeId(2), SeqNo::ZERO, SeqNo::from(2), ReplyOutcome::Ok(3));
+
+        let errors: Vec<_> = replies.errors().map(UpdateReply::to).collect();
+        assert_eq!(errors, vec![NodeId(1)]);
+
+        let outcomes: Vec<_> = replies
+            .into_inner()
+            .into_iter()
+            .map(|reply| reply.into_inner().3.into_result())
+            .collect();
+
+        assert_eq!(
+            outcomes,
+            vec![
+                Ok(1),
+                Err(ExecutionError::Malformed(String::from("bad"))),
+                Ok(3)
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_updates_become_error_outcomes() {
+        let app = SumApp::default();
+        let mut state = 0;
+
+        let mut batch = UpdateBatch::new(SeqNo::ZERO);
+        batch.push(update(1));
+        batch.push(update(REJECTED));
+
+        let replies = app.try_update_batch(&mut state, batch);
+
+        assert_eq!(replies.errors().count(), 1);
+
+        let outcome = replies.into_inner().remove(1).into_inner().3;
+
+        assert!(matches!(
+            outcome,
+            ReplyOutcome::Err(ExecutionError::Rejected(message)) if message.contains("not allowed")
+        ));
+
+        let typed: ReplyOutcome<u64> = Err(anyhow::Error::from(ExecutionError::Malformed(
+            String::from("bad"),
+        )))
+        .into();
+
+        assert!(matches!(
+            typed,
+            ReplyOutcome::Err(ExecutionError::Malformed(_))
+        ));
+    }
+
+    /// Records the operation id of every request it is told was executed
+    struct TimedApp {
+        timed: std::sync::Mutex<Vec<(SeqNo, Duration)>>,
+        wants_timing: bool,
+    }
+
+    impl TimedApp {
+        fn new(wants_timing: bool) -> Self {
+            TimedApp {
+                timed: Default::default(),
+                wants_timing,
+            }
+        }
+    }
+
+    impl Application<u64> for TimedApp {
+        type AppData = TestData;
+
+        fn initial_state() -> Result<u64> {
+            Ok(0)
+        }
+
+        fn unordered_execution(&self, state: &u64, request: u64) -> u64 {
+            *state + request
+        }
+
+        fn update(&self, state: &mut u64, request: u64) -> u64 {
+            *state += request;
+
+            *state
+        }
+
+        fn on_request_executed(&self, op_id: SeqNo, duration: Duration) {
+            self.timed.lock().unwrap().push((op_id, duration));
+        }
+
+        fn wants_request_timing(&self) -> bool {
+            self.wants_timing
+        }
+    }
+
+    #[test]
+    fn request_timing_is_reported_once_per_update() {
+        let app = TimedApp::new(true);
+        let mut state = 0;
+
+        let replies = app.update_batch(&mut state, batch_of(1, 4));
+
+        assert_eq!(replies.len(), 4);
+
+        let timed = app.timed.lock().unwrap();
+
+        assert_eq!(
+            timed.iter().map(|(op_id, _)| *op_id).collect::<Vec<_>>(),
+            (0..4).map(SeqNo::from).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn request_timing_is_opt_in() {
+        let app = TimedApp::new(false);
+        let mut state = 0;
+
+        app.update_batch(&mut state, batch_of(1, 4));
+
+        assert!(app.timed.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn partition_splits_by_session_parity() {
+        // Operations out of their natural order, with the operation id as the index in the input
+        let input = [5u64, 2, 0, 3, 4, 1];
+
+        let mut batch = UpdateBatch::new(SeqNo::from(4));
+
+        for (index, op) in input.iter().enumerate() {
+            batch.add(
+                NodeId(0),
+                SeqNo::from(*op as u32),
+                SeqNo::from(index as u32),
+                *op,
+            );
+        }
+
+        batch.append_batch_meta(BatchMeta::new());
+
+        let (even, odd) = batch.partition(|update| u32::from(update.session_id()) % 2 == 0);
+
+        assert_eq!(even.sequence_number(), SeqNo::from(4));
+        assert_eq!(odd.sequence_number(), SeqNo::from(4));
+
+        // Each partition keeps the relative order of the input
+        assert_eq!(operations(&even), vec![2, 0, 4]);
+        assert_eq!(operations(&odd), vec![5, 3, 1]);
+
+        let (mut even, mut odd) = (even, odd);
+
+        assert!(even.take_meta().is_some());
+        assert!(odd.take_meta().is_none());
+
+        // Merging both partitions back by their index in the input rebuilds it exactly
+        let mut even = even.into_inner().into_iter().peekable();
+        let mut odd = odd.into_inner().into_iter().peekable();
+        let mut rebuilt = Vec::with_capacity(input.len());
+
+        loop {
+            let next = match (even.peek(), odd.peek()) {
+                (Some(e), Some(o)) if e.operation_id() < o.operation_id() => even.next(),
+                (Some(_), Some(_)) => odd.next(),
+                (Some(_), None) => even.next(),
+                (None, Some(_)) => odd.next(),
+                (None, None) => break,
+            };
+
+            rebuilt.extend(next.map(|update| *update.operation()));
+        }
+
+        assert_eq!(rebuilt, input);
+    }
+
+    #[test]
+    fn contributing_nodes_are_deduplicated_and_sorted() {
+        let senders = [3, 1, 3, 2, 1];
+
+        let mut batch = UpdateBatch::new(SeqNo::ONE);
+        let mut unordered = UnorderedBatch::new();
+
+        for (op, from) in senders.iter().enumerate() {
+            let op = SeqNo::from(op as u32);
+
+            batch.add(NodeId(*from), SeqNo::ZERO, op, 0);
+            unordered.add(NodeId(*from), SeqNo::ZERO, op, 0);
+        }
+
+        let expected = vec![NodeId(1), NodeId(2), NodeId(3)];
+
+        assert_eq!(
+            batch.contributing_nodes().into_iter().collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(
+            unordered
+                .contributing_nodes()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn debug_prints_the_routing_fields() {
+        let update = Update::new(NodeId(1), SeqNo::from(2), SeqNo::from(3), 42u64);
+
+        assert_eq!(
+            format!("{:?}", update),
+            format!(
+                "Update {{ from: {:?}, session_id: {:?}, operation_id: {:?}, operation: 42, depends_on: None }}",
+                NodeId(1),
+                SeqNo::from(2),
+                SeqNo::from(3)
+            )
+        );
+
+        let mut batch = UpdateBatch::new(SeqNo::ONE);
+        batch.push(update);
+
+        let formatted = format!("{:?}", batch);
+
+        assert!(formatted.starts_with("UpdateBatch { "));
+        assert!(formatted.contains(&format!("seq_no: {:?}", SeqNo::ONE)));
+        assert!(formatted.contains("has_meta: false"));
+        assert!(formatted.contains("operation: 42"));
+
+        let formatted = format!("{:?}", replies_to(&[5]));
+
+        assert!(formatted.starts_with("BatchReplies { replies: [UpdateReply { "));
+        assert!(formatted.contains(&format!("to: {:?}", NodeId(5))));
+        assert!(formatted.contains("payload: 0"));
+    }
+
+    #[test]
+    fn size_bytes_of_an_empty_batch_is_the_overhead() {
+        let batch: UpdateBatch<Vec<u8>> = UpdateBatch::new(SeqNo::ONE);
+
+        assert_eq!(
+            batch.size_bytes(),
+            std::mem::size_of::<UpdateBatch<Vec<u8>>>()
+        );
+    }
+
+    #[test]
+    fn size_bytes_grows_with_each_operation() {
+        let empty = UpdateBatch::<Vec<u8>>::new(SeqNo::ONE).size_bytes();
+        let batch = bytes_batch(&[&[0; 100], &[0; 100], &[0; 100]]);
+
+        let per_update = std::mem::size_of::<Update<Vec<u8>>>() + 100;
+
+        assert_eq!(batch.size_bytes(), empty + 3 * per_update);
+    }
+
+    #[test]
+    fn well_ordered_sessions_are_valid() {
+        let mut batch = UpdateBatch::new(SeqNo::ONE);
+
+        batch.add(NodeId(1), SeqNo::ZERO, SeqNo::from(1), 0);
+        batch.add(NodeId(2), SeqNo::ZERO, SeqNo::from(0), 0);
+        batch.add(NodeId(1), SeqNo::ZERO, SeqNo::from(2), 0);
+        batch.add(NodeId(1), SeqNo::ONE, SeqNo::from(0), 0);
+
+        assert!(batch.validate_session_ordering().is_ok());
+    }
+
+    #[test]
+    fn session_regressions_name_the_session() {
+        let mut batch = UpdateBatch::new(SeqNo::ONE);
+
+        batch.add(NodeId(1), SeqNo::ZERO, SeqNo::from(1), 0);
+        batch.add(NodeId(2), SeqNo::ONE, SeqNo::from(5), 0);
+        batch.add(NodeId(2), SeqNo::ONE, SeqNo::from(4), 0);
+
+        let err = batch.validate_session_ordering().unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<BatchError>(),
+            Some(BatchError::SessionOrderViolation { from, session, previous, operation })
+                if *from == NodeId(2)
+                    && *session == SeqNo::ONE
+                    && *previous == SeqNo::from(5)
+                    && *operation == SeqNo::from(4)
+        ));
+    }
+
+    #[test]
+    fn unique_operations_are_accepted() {
+        assert!(batch_of(1, 4).check_unique_operations().is_ok());
+    }
+
+    #[test]
+    fn duplicated_operations_are_listed() {
+        let mut batch = batch_of(1, 3);
+        batch.push(update(1));
+        batch.push(update(1));
+
+        let err = batch.check_unique_operations().unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<BatchError>(),
+            Some(BatchError::DuplicateOperations(duplicates))
+                if duplicates == &vec![(NodeId(0), SeqNo::ZERO, SeqNo::from(1))]
+        ));
+    }
+
+    #[test]
+    fn promoting_an_unordered_batch_keeps_its_updates() {
+        let mut unordered = UnorderedBatch::new();
+
+        for op in 0..3 {
+            unordered.add(NodeId(0), SeqNo::ZERO, SeqNo::from(op as u32), op);
+        }
+
+        let mut ordered = unordered.into_ordered(SeqNo::from(9));
+
+        assert_eq!(ordered.sequence_number(), SeqNo::from(9));
+        assert_eq!(operations(&ordered), vec![0, 1, 2]);
+        assert!(ordered.take_meta().is_none());
+    }
+
+    #[test]
+    fn sort_by_destination_is_stable_and_uses_every_key() {
+        let mut replies = BatchReplies::with_capacity(6);
+
+        replies.add(NodeId(2), SeqNo::ZERO, SeqNo::ZERO, 0);
+        replies.add(NodeId(1), SeqNo::ONE, SeqNo::ZERO, 1);
+        replies.add(NodeId(1), SeqNo::ZERO, SeqNo::ONE, 2);
+        replies.add(NodeId(1), SeqNo::ZERO, SeqNo::ZERO, 3);
+        // Same key as the previous reply, so it must stay after it
+        replies.add(NodeId(1), SeqNo::ZERO, SeqNo::ZERO, 4);
+        replies.add(NodeId(0), SeqNo::ONE, SeqNo::ONE, 5);
+
+        replies.sort_by_destination();
+
+        assert_eq!(
+            replies
+                .into_inner()
+                .into_iter()
+                .map(|reply| reply.into_inner().3)
+                .collect::<Vec<_>>(),
+            vec![5, 3, 4, 2, 1, 0]
+        );
+    }
+
+    #[test]
+    fn recycled_replies_start_empty_and_keep_their_capacity() {
+        let pool = ReplyPool::new(1);
+
+        let mut replies = pool.get(4);
+
+        for op in 0..64 {
+            replies.add(NodeId(0), SeqNo::ZERO, SeqNo::from(op), op as u64);
+        }
+
+        let capacity = replies.capacity();
+
+        pool.recycle(replies);
+
+        let reused = pool.get(4);
+
+        assert!(reused.is_empty());
+        assert!(reused.capacity() >= capacity);
+    }
+
+    #[test]
+    fn pool_holds_at_most_max_pooled_allocations() {
+        let pool: ReplyPool<u64> = ReplyPool::new(1);
+
+        pool.recycle(BatchReplies::with_capacity(128));
+        pool.recycle(BatchReplies::with_capacity(256));
+
+        assert!(pool.get(0).capacity() >= 128);
+        assert_eq!(pool.get(0).capacity(), 0);
+    }
+
+    #[test]
+    fn summary_counts_replies_and_destinations() {
+        let summary = replies_to(&[1, 2, 1, 3, 2, 1]).summary();
+
+        assert_eq!(summary.reply_count(), 6);
+        assert_eq!(summary.distinct_nodes(), 3);
+        assert_eq!(summary.payload_bytes(), None);
+    }
+
+    #[test]
+    fn summary_with_size_adds_the_payload_bytes() {
+        let mut replies = BatchReplies::with_capacity(2);
+
+        replies.add(NodeId(1), SeqNo::ZERO, SeqNo::ZERO, vec![0u8; 10]);
+        replies.add(NodeId(2), SeqNo::ZERO, SeqNo::ONE, vec![0u8; 20]);
+
+        let summary = replies.summary_with_size();
+
+        assert_eq!(summary.reply_count(), 2);
+        assert_eq!(summary.distinct_nodes(), 2);
+        assert_eq!(
+            summary.payload_bytes(),
+            Some(2 * std::mem::size_of::<Vec<u8>>() + 30)
+        );
+    }
+
+    #[test]
+    fn applications_are_unnamed_by_default() {
+        let app = SumApp::default();
+
+        assert_eq!(app.name(), "unnamed-application");
+    }
+
+    #[test]
+    fn seeds_are_deterministic_per_sequence_number() {
+        let app = SumApp::default();
+
+        assert_eq!(app.seed_for(SeqNo::from(7)), app.seed_for(SeqNo::from(7)));
+        assert_eq!(
+            SumApp::default().seed_for(SeqNo::from(7)),
+            app.seed_for(SeqNo::from(7))
+        );
+        assert_ne!(app.seed_for(SeqNo::from(7)), app.seed_for(SeqNo::from(8)));
+    }
+
+    #[test]
+    fn mapping_payloads_keeps_the_routing_fields() {
+        let mut replies = BatchReplies::with_capacity(2);
+
+        replies.add(NodeId(3), SeqNo::from(1), SeqNo::from(10), 7u64);
+        replies.add(NodeId(4), SeqNo::from(2), SeqNo::from(20), 8u64);
+
+        let mapped = replies.map_payloads(|payload| payload.to_string());
+
+        assert_eq!(
+            mapped
+                .into_inner()
+                .into_iter()
+                .map(UpdateReply::into_inner)
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    NodeId(3),
+                    SeqNo::from(1),
+                    SeqNo::from(10),
+                    String::from("7")
+                ),
+                (
+                    NodeId(4),
+                    SeqNo::from(2),
+                    SeqNo::from(20),
+                    String::from("8")
+                ),
+            ]
+        );
+
+        let reply = UpdateReply::init(NodeId(5), SeqNo::ONE, SeqNo::ONE, 1u64).map(|p| p + 1);
+
+        assert_eq!(reply.into_inner(), (NodeId(5), SeqNo::ONE, SeqNo::ONE, 2));
+    }
+
+    #[test]
+    fn epoch_round_trips_through_batch_helpers() {
+        assert_eq!(batch_of(1, 1).epoch(), None);
+
+        let batch = batch_of(1, 4).with_epoch(3);
+
+        assert_eq!(batch.epoch(), Some(3));
+
+        let chunks = batch.split_into_chunks(2);
+
+        assert!(chunks.iter().all(|chunk| chunk.epoch() == Some(3)));
+
+        let mut merged = batch_of(1, 1);
+        merged.merge(chunks.into_iter().next().unwrap()).unwrap();
+
+        assert_eq!(merged.epoch(), Some(3));
+
+        let (matching, remaining) = merged.partition(|update| *update.operation() == 0);
+
+        assert_eq!(matching.epoch(), Some(3));
+        assert_eq!(remaining.epoch(), Some(3));
+    }
+
+    #[test]
+    fn batches_of_different_epochs_are_not_merged() {
+        let mut batch = batch_of(1, 1).with_epoch(1);
+
+        let err = batch.merge(batch_of(2, 1).with_epoch(2)).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<BatchError>(),
+            Some(BatchError::MergeEpochMismatch {
+                current: 1,
+                other: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn node_batches_follow_first_appearance_and_per_node_order() {
+        let batches = replies_to(&[2, 1, 2, 3, 1, 2]).into_node_batches();
+
+        let payloads = batches
+            .into_iter()
+            .map(|(node, replies)| {
+                let ops = replies
+                    .into_iter()
+                    .map(|(session, op, payload)| {
+                        assert_eq!(session, SeqNo::ZERO);
+                        assert_eq!(op, SeqNo::from(payload as u32));
+
+                        payload
+                    })
+                    .collect::<Vec<_>>();
+
+                (node, ops)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            payloads,
+            vec![
+                (NodeId(2), vec![0, 2, 5]),
+                (NodeId(1), vec![1, 4]),
+                (NodeId(3), vec![3]),
+            ]
+        );
+    }
+
+    #[test]
+    fn dry_run_detects_rejected_operations_without_side_effects() {
+        let app = SumApp::default();
+        let state = 10;
+
+        let mut batch = batch_of(1, 3);
+        batch.push(update(REJECTED));
+
+        let err = app.dry_run_batch(&state, &batch).unwrap_err();
+
+        assert!(err.to_string().contains(&REJECTED.to_string()));
+        assert_eq!(state, 10);
+        assert_eq!(app.pre_batches.load(AtomicOrdering::Relaxed), 0);
+        assert_eq!(app.post_batches.load(AtomicOrdering::Relaxed), 0);
+
+        assert!(app.dry_run_batch(&state, &batch_of(2, 3)).is_ok());
+    }
+
+    /// An operation carrying an optional idempotency key
+    struct Keyed(Option<u32>);
+
+    impl IdempotentOperation for Keyed {
+        type IdKey = u32;
+
+        fn idempotency_key(&self) -> Option<u32> {
+            self.0
+        }
+    }
+
+    fn keyed_batch(seq: u32, keys: &[Option<u32>]) -> UpdateBatch<Keyed> {
+        let mut batch = UpdateBatch::new(SeqNo::from(seq));
+
+        for (op, key) in keys.iter().enumerate() {
+            batch.add(NodeId(0), SeqNo::ZERO, SeqNo::from(op as u32), Keyed(*key));
+        }
+
+        batch
+    }
+
+    fn keys(batch: &UpdateBatch<Keyed>) -> Vec<Option<u32>> {
+        batch.iter().map(|update| update.operation().0).collect()
+    }
+
+    #[test]
+    fn keys_seen_in_previous_batches_are_dropped() {
+        let mut seen = HashSet::new();
+
+        let mut first = keyed_batch(1, &[Some(1), Some(2), Some(1)]);
+
+        assert_eq!(first.dedup_idempotent(&mut seen), 1);
+        assert_eq!(keys(&first), vec![Some(1), Some(2)]);
+
+        let mut second = keyed_batch(2, &[Some(2), None, Some(3), None]);
+
+        assert_eq!(second.dedup_idempotent(&mut seen), 1);
+        assert_eq!(keys(&second), vec![None, Some(3), None]);
+        assert_eq!(seen, HashSet::from([1, 2, 3]));
+    }
+
+    #[test]
+    fn streamed_replies_follow_execution_order() {
+        let app = SumApp::default();
+        let mut state = 0;
+        let mut streamed = Vec::new();
+
+        app.update_batch_streaming(&mut state, batch_of(1, 4), &mut |reply| {
+            streamed.push(reply.into_inner())
+        });
+
+        // Each reply is the running sum of the operations executed so far
+        assert_eq!(
+            streamed
+                .into_iter()
+                .map(|(_, _, op_id, sum)| (op_id, sum))
+                .collect::<Vec<_>>(),
+            vec![
+                (SeqNo::from(0), 0),
+                (SeqNo::from(1), 1),
+                (SeqNo::from(2), 3),
+                (SeqNo::from(3), 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn iterating_does_not_consume_the_unordered_batch() {
+        let mut batch = UnorderedBatch::new();
+
+        for op in 0..3 {
+            batch.add(NodeId(op as u32), SeqNo::ZERO, SeqNo::from(op as u32), op);
+        }
+
+        assert_eq!(operations(batch.iter()), vec![0, 1, 2]);
+        assert_eq!(operations(&batch), vec![0, 1, 2]);
+
+        for update in batch.iter_mut() {
+            update.set_deadline(Some(Instant::now()));
+        }
+
+        assert!(batch.iter().all(|update| update.deadline().is_some()));
+
+        for update in &mut batch {
+            update.set_deadline(None);
+        }
+
+        assert!(batch.iter().all(|update| update.deadline().is_none()));
+        assert_eq!(batch.contributing_nodes().len(), 3);
+
+        let owned: Vec<_> = batch
+            .into_iter()
+            .map(|update| update.into_inner().3)
+            .collect();
+
+        assert_eq!(owned, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn exec_units_carry_contiguous_indices_and_the_sequence_number() {
+        let units = batch_of(6, 4).into_exec_units();
+
+        assert_eq!(units.len(), 4);
+
+        for (expected, unit) in units.enumerate() {
+            let (seq, index, update) = unit.into_inner();
+
+            assert_eq!(seq, SeqNo::from(6));
+            assert_eq!(index, expected);
+            assert_eq!(*update.operation(), expected as u64);
+        }
+    }
+
+    /// Records the operation id and operation of every update it visits
+    #[derive(Default)]
+    struct RecordingVisitor {
+        visited: Vec<(SeqNo, u64)>,
+    }
+
+    impl UpdateVisitor<u64> for RecordingVisitor {
+        fn visit(
+            &mut self,
+            _from: NodeId,
+            _session_id: SeqNo,
+            operation_id: SeqNo,
+            operation: &u64,
+        ) {
+            self.visited.push((operation_id, *operation));
+        }
+    }
+
+    #[test]
+    fn visitor_sees_each_update_once_in_order() {
+        let batch = batch_of(1, 4);
+        let mut visitor = RecordingVisitor::default();
+
+        batch.visit(&mut visitor);
+
+        assert_eq!(
+            visitor.visited,
+            (0..4)
+                .map(|op| (SeqNo::from(op as u32), op))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(batch.len(), 4);
+    }
+
+    fn routing(update: &Update<impl Sized>) -> (NodeId, SeqNo, SeqNo) {
+        (update.from(), update.session_id(), update.operation_id())
+    }
+
+    #[test]
+    fn metadata_only_clone_keeps_the_routing_fields() {
+        let mut batch = UpdateBatch::new(SeqNo::from(8));
+
+        for op in 0..3u32 {
+            batch.add(
+                NodeId(op),
+                SeqNo::from(op + 1),
+                SeqNo::from(op + 2),
+                vec![0u8; 1024],
+            );
+        }
+
+        let shape = batch.clone_metadata_only();
+
+        assert_eq!(shape.sequence_number(), SeqNo::from(8));
+        assert_eq!(
+            shape.iter().map(routing).collect::<Vec<_>>(),
+            batch.iter().map(routing).collect::<Vec<_>>()
+        );
+
+        let borrowed = batch.as_ref().first().unwrap().as_ref_operation();
+
+        assert_eq!(borrowed.operation().len(), 1024);
+    }
+
+    fn unordered_from(senders: &[u32]) -> UnorderedBatch<u64> {
+        let mut batch = UnorderedBatch::new();
+
+        for (op, from) in senders.iter().enumerate() {
+            batch.add(
+                NodeId(*from),
+                SeqNo::ZERO,
+                SeqNo::from(op as u32),
+                op as u64,
+            );
+        }
+
+        batch
+    }
+
+    #[test]
+    fn grouped_unordered_execution_matches_grouping_the_flat_replies() {
+        let app = SumApp::default();
+        let state = 100;
+        let senders = [1, 2, 1, 3, 2, 1];
+
+        let into_payloads = |replies: Vec<UpdateReply<u64>>| -> Vec<(SeqNo, u64)> {
+            replies
+                .into_iter()
+                .map(|reply| {
+                    let (_, _, op_id, payload) = reply.into_inner();
+
+                    (op_id, payload)
+                })
+                .collect()
+        };
+
+        let mut grouped = app.unordered_batched_execution_grouped(&state, unordered_from(&senders));
+        let flat = app
+            .unordered_batched_execution(&state, unordered_from(&senders))
+            .group_by_node();
+
+        assert_eq!(grouped.len(), flat.len());
+
+        for (node, replies) in flat {
+            let grouped_replies = grouped.remove(&node).unwrap();
+
+            assert_eq!(
+                into_payloads(grouped_replies.into_inner()),
+                into_payloads(replies)
+            );
+        }
+    }
+
+    #[test]
+    fn try_add_accepts_updates_up_to_the_bound() {
+        let mut batch = UpdateBatch::new_bounded(SeqNo::ONE, 3);
+
+        for op in 0..3 {
+            batch
+                .try_add(NodeId(0), SeqNo::ZERO, SeqNo::from(op), u64::from(op))
+                .unwrap();
+        }
+
+        let err = batch
+            .try_add(NodeId(0), SeqNo::ZERO, SeqNo::from(3), 3)
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<BatchError>(),
+            Some(BatchError::BatchFull { max: 3 })
+        ));
+        assert_eq!(operations(&batch), vec![0, 1, 2]);
+        assert_eq!(batch.max_len(), Some(3));
+    }
+
+    #[test]
+    fn huge_bounds_do_not_preallocate() {
+        let mut batch = UpdateBatch::new_bounded(SeqNo::ONE, usize::MAX);
+
+        batch
+            .try_add(NodeId(0), SeqNo::ZERO, SeqNo::ZERO, 0u64)
+            .unwrap();
+
+        assert_eq!(batch.len(), 1);
+    }
+
+    fn replies_for(ops: std::ops::Range<u32>) -> BatchReplies<u64> {
+        let mut replies = BatchReplies::with_capacity(ops.len());
+
+        for op in ops {
+            replies.add(NodeId(0), SeqNo::ZERO, SeqNo::from(op), u64::from(op));
+        }
+
+        replies
+    }
+
+    #[test]
+    fn reassembling_out_of_order_chunks_restores_the_request_order() {
+        let chunks = vec![
+            (5, replies_for(5..7)),
+            (0, replies_for(0..2)),
+            (2, replies_for(2..5)),
+        ];
+
+        let reassembled = BatchReplies::reassemble(chunks);
+
+        assert_eq!(
+            reassembled
+                .into_inner()
+                .into_iter()
+                .map(|reply| reply.into_inner().3)
+                .collect::<Vec<_>>(),
+            (0..7).collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "does not follow")]
+    fn reassembling_chunks_with_a_gap_triggers_the_debug_assertion() {
+        BatchReplies::reassemble(vec![(0, replies_for(0..2)), (3, replies_for(3..5))]);
+    }
+
+    /// An application which only mutates its state, so it has no read only path
+    struct WriteOnlyApp;
+
+    impl Application<u64> for WriteOnlyApp {
+        type AppData = TestData;
+
+        fn initial_state() -> Result<u64> {
+            Ok(0)
+        }
+
+        fn unordered_execution(&self, state: &u64, _request: u64) -> u64 {
+            *state
+        }
+
+        fn update(&self, state: &mut u64, request: u64) -> u64 {
+            *state = request;
+
+            *state
+        }
+
+        fn supports_unordered(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn unordered_support_is_reported_by_the_application() {
+        assert!(SumApp::default().supports_unordered());
+        assert!(!WriteOnlyApp.supports_unordered());
+    }
+
+    #[test]
+    fn satisfied_dependencies_are_valid() {
+        let mut batch = batch_of(5, 3);
+
+        assert!(batch.iter().all(|update| update.depends_on().is_none()));
+
+        for update in batch.iter_mut() {
+            update.set_depends_on(Some(SeqNo::from(4)));
+        }
+
+        batch.push(update(3).with_depends_on(SeqNo::from(2)));
+
+        assert!(batch.validate_dependencies(SeqNo::from(4)).is_ok());
+    }
+
+    #[test]
+    fn violated_dependencies_name_the_update() {
+        let mut batch = batch_of(5, 3);
+        batch.push(update(3).with_depends_on(SeqNo::from(6)));
+
+        let err = batch.validate_dependencies(SeqNo::from(4)).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<BatchError>(),
+            Some(BatchError::UnsatisfiedDependency { operation, depends_on, executed_up_to, .. })
+                if *operation == SeqNo::from(3)
+                    && *depends_on == SeqNo::from(6)
+                    && *executed_up_to == SeqNo::from(4)
+        ));
+
+        for update in batch.iter_mut() {
+            update.set_depends_on(None);
+        }
+
+        assert!(batch.validate_dependencies(SeqNo::from(4)).is_ok());
+    }
+
+    /// The indices of the batches (of the given sizes) after which `policy` takes a checkpoint
+    fn checkpoints_taken(policy: CheckpointPolicy, batch_sizes: &[u64]) -> Vec<usize> {
+        let (mut batches, mut ops) = (0, 0);
+        let mut taken = Vec::new();
+
+        for (index, size) in batch_sizes.iter().enumerate() {
+            batches += 1;
+            ops += size;
+
+            if policy.should_checkpoint(batches, ops) {
+                taken.push(index);
+                (batches, ops) = (0, 0);
+            }
+        }
+
+        taken
+    }
+
+    #[test]
+    fn every_n_ops_triggers_at_the_cumulative_count() {
+        let sizes = [3, 4, 2, 5, 1, 7];
+
+        assert_eq!(
+            checkpoints_taken(CheckpointPolicy::EveryNOps(8), &sizes),
+            vec![2, 5]
+        );
+        assert_eq!(
+            checkpoints_taken(CheckpointPolicy::EveryNBatches(2), &sizes),
+            vec![1, 3, 5]
+        );
+        assert!(checkpoints_taken(CheckpointPolicy::Manual, &sizes).is_empty());
+        assert!(checkpoints_taken(CheckpointPolicy::EveryNOps(0), &sizes).is_empty());
+        assert_eq!(
+            SumApp::default().checkpoint_policy(),
+            CheckpointPolicy::Manual
+        );
+    }
+
+    #[test]
+    fn suggest_checkpoint_counts_the_batch() {
+        let batch = batch_of(1, 3);
+
+        assert!(!batch.suggest_checkpoint(4, 8));
+        assert!(batch.suggest_checkpoint(5, 8));
+        assert!(!batch.suggest_checkpoint(100, 0));
+    }
+
+    #[test]
+    fn replies_in_session_order_pass_the_ordering_check() {
+        let mut replies = BatchReplies::with_capacity(4);
+
+        replies.add(NodeId(1), SeqNo::ZERO, SeqNo::ZERO, 0u64);
+        replies.add(NodeId(2), SeqNo::ZERO, SeqNo::ONE, 1u64);
+        replies.add(NodeId(1), SeqNo::ZERO, SeqNo::ONE, 2u64);
+        replies.add(NodeId(1), SeqNo::ONE, SeqNo::ZERO, 3u64);
+
+        assert!(replies.assert_session_ordering().is_ok());
+    }
+
+    #[test]
+    fn shuffled_replies_fail_the_ordering_check_naming_the_session() {
+        let mut replies = BatchReplies::with_capacity(3);
+
+        replies.add(NodeId(1), SeqNo::ZERO, SeqNo::ZERO, 0u64);
+        replies.add(NodeId(2), SeqNo::ONE, SeqNo::from(5u32), 1u64);
+        replies.add(NodeId(2), SeqNo::ONE, SeqNo::from(4u32), 2u64);
+
+        let error = replies.assert_session_ordering().unwrap_err();
+
+        match error.downcast_ref::<BatchError>() {
+            Some(BatchError::ReplyOrderViolation {
+                to,
+                session,
+                previous,
+                operation,
+            }) => {
+                assert_eq!(*to, NodeId(2));
+                assert_eq!(*session, SeqNo::ONE);
+                assert_eq!(*previous, SeqNo::from(5u32));
+                assert_eq!(*operation, SeqNo::from(4u32));
+            }
+            other => panic!("Expected a reply order violation, got {:?}", other),
+        }
+    }
+
+    /// Relies on executors starting it before handing it any update
+    #[derive(Default)]
+    struct StartedApp {
+        started: std::sync::atomic::AtomicBool,
+    }
+
+    impl Application<u64> for StartedApp {
+        type AppData = TestData;
+
+        fn initial_state() -> Result<u64> {
+            Ok(0)
+        }
+
+        fn on_start(&self, state: &mut u64) -> Result<()> {
+            // Warm up the state, as if loading a cache
+            *state += 100;
+
+            self.started
+                .store(true, std::sync::atomic::Ordering::SeqCst);
+
+            Ok(())
+        }
+
+        fn unordered_execution(&self, state: &u64, request: u64) -> u64 {
+            *state + request
+        }
+
+        fn update(&self, state: &mut u64, request: u64) -> u64 {
+            assert!(
+                self.started.load(std::sync::atomic::Ordering::SeqCst),
+                "Update executed before on_start"
+            );
+
+            *state += request;
+
+            *state
+        }
+    }
+
+    #[test]
+    fn on_start_prepares_the_live_state() {
+        let app = StartedApp::default();
+        let mut state = StartedApp::initial_state().unwrap();
+
+        app.on_start(&mut state).unwrap();
+
+        let replies = app.update_batch(&mut state, batch_of(1, 3));
+
+        assert_eq!(replies.len(), 3);
+        assert_eq!(state, 103);
+    }
+
+    #[test]
+    fn on_start_defaults_to_a_no_op() {
+        let app = TimedApp::new(false);
+        let mut state = 7;
+
+        app.on_start(&mut state).unwrap();
+
+        assert_eq!(state, 7);
+    }
+
+    /// The proposer assembles the batch from the requests it received, dropping the expired
+    /// ones before proposing it, so every replica orders and executes the same updates
+    #[test]
+    fn expired_updates_are_dropped_by_the_proposer_before_ordering() {
+        let received_at = Instant::now();
+
+        let pending = vec![
+            update(0).with_deadline(received_at - Duration::from_millis(10)),
+            update(1).with_deadline(received_at + Duration::from_secs(60)),
+            update(2),
+        ];
+
+        let mut proposal = UpdateBatch::new(SeqNo::from(7u32));
+
+        for update in pending {
+            proposal.push(update);
+        }
+
+        assert_eq!(proposal.drop_expired(received_at), 1);
+
+        assert_eq!(operations(proposal.iter()), vec![1, 2]);
+    }
+
+    #[cfg(feature = "serialize_serde")]
+    #[test]
+    fn ordered_batches_carry_no_deadlines() {
+        let mut proposal = UpdateBatch::new(SeqNo::from(7u32));
+
+        proposal.push(update(0).with_deadline(Instant::now() - Duration::from_secs(1)));
+
+        let json = serde_json::to_string(&proposal).unwrap();
+        let mut ordered: UpdateBatch<u64> = serde_json::from_str(&json).unwrap();
+
+        assert!(ordered.iter().all(|update| update.deadline().is_none()));
+
+        // Executors of the ordered batch can never drop any of its updates
+        assert_eq!(ordered.drop_expired(Instant::now()), 0);
+        assert_eq!(ordered.len(), 1);
+    }
+
+    /// Overrides the timing hook but not `wants_request_timing()`
+    #[derive(Default)]
+    struct HookOnlyApp {
+        timed: std::sync::Mutex<Vec<SeqNo>>,
+    }
+
+    impl Application<u64> for HookOnlyApp {
+        type AppData = TestData;
+
+        fn initial_state() -> Result<u64> {
+            Ok(0)
+        }
+
+        fn unordered_execution(&self, state: &u64, request: u64) -> u64 {
+            *state + request
+        }
+
+        fn update(&self, state: &mut u64, request: u64) -> u64 {
+            *state += request;
+
+            *state
+        }
+
+        fn on_request_executed(&self, op_id: SeqNo, _duration: Duration) {
+            self.timed.lock().unwrap().push(op_id);
+        }
+    }
+
+    #[test]
+    fn overriding_only_the_timing_hook_leaves_it_unused() {
+        let app = HookOnlyApp::default();
+        let mut state = 0;
+
+        let replies = app.update_batch(&mut state, batch_of(1, 4));
+
+        assert_eq!(replies.len(), 4);
+        assert!(!app.wants_request_timing());
+        assert!(app.timed.lock().unwrap().is_empty());
+    }
+
+    /// Records the threads its requests were executed on
+    struct PooledApp {
+        pool: ThreadPool,
+        threads: std::sync::Mutex<Vec<Option<String>>>,
+    }
+
+    impl PooledApp {
+        fn new() -> Self {
+            PooledApp {
+                pool: rayon::ThreadPoolBuilder::new()
+                    .num_threads(2)
+                    .thread_name(|index| format!("execution-{index}"))
+                    .build()
+                    .unwrap(),
+                threads: Default::default(),
+            }
+        }
+
+        fn threads(&self) -> Vec<Option<String>> {
+            std::mem::take(&mut *self.threads.lock().unwrap())
+        }
+    }
+
+    impl Application<u64> for PooledApp {
+        type AppData = TestData;
+
+        fn initial_state() -> Result<u64> {
+            Ok(0)
+        }
+
+        fn unordered_execution(&self, state: &u64, request: u64) -> u64 {
+            *state + request
+        }
+
+        fn update(&self, state: &mut u64, request: u64) -> u64 {
+            self.update_commutative(state, request)
+        }
+
+        fn is_commutative(&self) -> bool {
+            true
+        }
+    }
+
+    impl CommutativeApplication<u64> for PooledApp {
+        fn update_commutative(&self, state: &u64, request: u64) -> u64 {
+            let thread = std::thread::current().name().map(str::to_string);
+            self.threads.lock().unwrap().push(thread);
+
+            *state + request
+        }
+
+        fn execution_pool(&self) -> Option<&ThreadPool> {
+            Some(&self.pool)
+        }
+    }
+
+    #[test]
+    fn parallel_execution_runs_on_the_execution_pool() {
+        let app = PooledApp::new();
+        let mut state = 0;
+
+        let replies = app.execute_batch_parallel(&mut state, batch_of(0, 16));
+
+        assert_eq!(replies.len(), 16);
+
+        let threads = app.threads();
+
+        assert_eq!(threads.len(), 16);
+        assert!(threads.iter().all(|thread| thread
+            .as_deref()
+            .is_some_and(|name| name.starts_with("execution-"))));
+    }
+
+    #[test]
+    fn update_batch_never_dispatches_to_the_execution_pool() {
+        let app = PooledApp::new();
+        let mut state = 0;
+
+        app.update_batch(&mut state, batch_of(0, 16));
+
+        let caller = std::thread::current().name().map(str::to_string);
+
+        assert!(app.threads().iter().all(|thread| *thread == caller));
+    }
+
+    #[test]
+    fn debug_elides_large_payloads_and_batches() {
+        let update = Update::new(NodeId(0), SeqNo::ZERO, SeqNo::ZERO, "x".repeat(1000));
+
+        let formatted = format!("{:?}", update);
+
+        assert!(formatted.contains(&format!(
+            "operation: \"{}...",
+            "x".repeat(DEBUG_PAYLOAD_CHARS - 1)
+        )));
+        assert!(formatted.len() < 200);
+
+        let formatted = format!("{:?}", batch_of(0, 20));
+
+        assert_eq!(formatted.matches("Update {").count(), DEBUG_ENTRIES);
+        assert!(formatted.contains(&format!("... {} more", 20 - DEBUG_ENTRIES)));
+
+        let formatted = format!("{:?}", replies_to(&[0; 20]));
+
+        assert_eq!(formatted.matches("UpdateReply {").count(), DEBUG_ENTRIES);
+        assert!(formatted.contains(&format!("... {} more", 20 - DEBUG_ENTRIES)));
     }
 }
@@ -0,0 +1,620 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use atlas_common::error::*;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+
+use crate::app::{
+    Application, BatchReplies, Reply, ReplyOutcome, Request, UnorderedBatch, UpdateBatch,
+};
+use crate::serialize::ApplicationData;
+
+/// Requests which belong to a namespace, used by [`RoutedApplication`] to pick the
+/// sub-application that serves them
+pub trait NamespacedRequest {
+    /// The namespace of the service this request is destined to.
+    fn namespace(&self) -> &str;
+}
+
+/// An object safe view over an [`Application`], so applications of different types
+/// (but with the same application data) can be hosted by a [`RoutedApplication`].
+///
+/// Implemented for every [`Application`], forwarding to its own implementation.
+pub trait SubApplication<S, D>: Send + Sync
+where
+    D: ApplicationData,
+{
+    fn name(&self) -> &'static str;
+
+    fn on_start(&self, state: &mut S) -> Result<()>;
+
+    fn unordered_execution(&self, state: &S, request: D::Request) -> D::Reply;
+
+    fn unordered_batched_execution(
+        &self,
+        state: &S,
+        requests: UnorderedBatch<D::Request>,
+    ) -> BatchReplies<D::Reply>;
+
+    fn update(&self, state: &mut S, request: D::Request) -> D::Reply;
+
+    fn try_update(&self, state: &mut S, request: D::Request) -> Result<D::Reply>;
+
+    fn pre_batch(&self, state: &mut S, seq: SeqNo);
+
+    fn post_batch(&self, state: &mut S, seq: SeqNo);
+
+    fn on_request_executed(&self, op_id: SeqNo, duration: Duration);
+
+    fn wants_request_timing(&self) -> bool;
+}
+
+impl<S, A> SubApplication<S, A::AppData> for A
+where
+    A: Application<S>,
+{
+    fn name(&self) -> &'static str {
+        Application::name(self)
+    }
+
+    fn on_start(&self, state: &mut S) -> Result<()> {
+        Application::on_start(self, state)
+    }
+
+    fn unordered_execution(&self, state: &S, request: Request<A, S>) -> Reply<A, S> {
+        Application::unordered_execution(self, state, request)
+    }
+
+    fn unordered_batched_execution(
+        &self,
+        state: &S,
+        requests: UnorderedBatch<Request<A, S>>,
+    ) -> BatchReplies<Reply<A, S>> {
+        Application::unordered_batched_execution(self, state, requests)
+    }
+
+    fn update(&self, state: &mut S, request: Request<A, S>) -> Reply<A, S> {
+        Application::update(self, state, request)
+    }
+
+    fn try_update(&self, state: &mut S, request: Request<A, S>) -> Result<Reply<A, S>> {
+        Application::try_update(self, state, request)
+    }
+
+    fn pre_batch(&self, state: &mut S, seq: SeqNo) {
+        Application::pre_batch(self, state, seq)
+    }
+
+    fn post_batch(&self, state: &mut S, seq: SeqNo) {
+        Application::post_batch(self, state, seq)
+    }
+
+    fn on_request_executed(&self, op_id: SeqNo, duration: Duration) {
+        Application::on_request_executed(self, op_id, duration)
+    }
+
+    fn wants_request_timing(&self) -> bool {
+        Application::wants_request_timing(self)
+    }
+}
+
+/// An application hosting multiple logical services behind a single replicated state
+/// machine, dispatching each request to the sub-application registered for its namespace
+/// (see [`NamespacedRequest`]).
+///
+/// Requests of namespaces with no registered sub-application are served by the fallback,
+/// so dispatching is deterministic across replicas. All the sub-applications share the
+/// same state, whose initial value is provided by the fallback, which also serves reads.
+///
+/// The requests of a batch are executed in order, each with the `update()` of its
+/// sub-application (so overrides of `update_batch()` in sub-applications are not used).
+/// The `pre_batch()` and `post_batch()` hooks of every sub-application serving requests of
+/// the batch are invoked once per batch, around all of its requests, in the order in which
+/// the sub-applications first appear in the batch.
+pub struct RoutedApplication<S, F>
+where
+    F: Application<S>,
+{
+    routes: BTreeMap<String, Box<dyn SubApplication<S, F::AppData>>>,
+    fallback: F,
+}
+
+impl<S, F> RoutedApplication<S, F>
+where
+    F: Application<S>,
+{
+    /// Creates a routed application with no routes, serving every request with `fallback`.
+    pub fn new(fallback: F) -> Self {
+        Self {
+            routes: BTreeMap::new(),
+            fallback,
+        }
+    }
+
+    /// Routes the requests of `namespace` to `app`, replacing any previous route.
+    pub fn with_route<A>(mut self, namespace: impl Into<String>, app: A) -> Self
+    where
+        A: SubApplication<S, F::AppData> + 'static,
+    {
+        self.routes.insert(namespace.into(), Box::new(app));
+        self
+    }
+
+    /// The namespaces with a registered sub-application, in order.
+    pub fn namespaces(&self) -> impl Iterator<Item = &str> {
+        self.routes.keys().map(String::as_str)
+    }
+
+    /// The application serving the requests with no registered sub-application.
+    pub fn fallback(&self) -> &F {
+        &self.fallback
+    }
+
+    /// The sub-application serving the requests of `namespace`.
+    pub fn route(&self, namespace: &str) -> &dyn SubApplication<S, F::AppData> {
+        self.target(self.route_key(namespace))
+    }
+
+    /// The registered namespace serving the requests of `namespace`,
+    /// or `None` if they are served by the fallback.
+    fn route_key(&self, namespace: &str) -> Option<&str> {
+        self.routes
+            .get_key_value(namespace)
+            .map(|(namespace, _)| namespace.as_str())
+    }
+
+    fn target(&self, route_key: Option<&str>) -> &dyn SubApplication<S, F::AppData> {
+        match route_key.and_then(|namespace| self.routes.get(namespace)) {
+            Some(app) => app.as_ref(),
+            None => &self.fallback,
+        }
+    }
+
+    /// The sub-applications serving the requests of `batch`, in the order in which
+    /// they first appear in it.
+    fn targets_of(
+        &self,
+        batch: &UpdateBatch<Request<F, S>>,
+    ) -> Vec<&dyn SubApplication<S, F::AppData>>
+    where
+        Request<F, S>: NamespacedRequest,
+    {
+        let mut route_keys = Vec::new();
+
+        for update in batch.iter() {
+            let route_key = self.route_key(update.operation().namespace());
+
+            if !route_keys.contains(&route_key) {
+                route_keys.push(route_key);
+            }
+        }
+
+        route_keys
+            .into_iter()
+            .map(|route_key| self.target(route_key))
+            .collect()
+    }
+}
+
+impl<S, F> Application<S> for RoutedApplication<S, F>
+where
+    F: Application<S>,
+    Request<F, S>: NamespacedRequest,
+{
+    type AppData = F::AppData;
+
+    fn initial_state() -> Result<S> {
+        F::initial_state()
+    }
+
+    /// Starts the fallback and then every sub-application, in namespace order.
+    fn on_start(&self, state: &mut S) -> Result<()> {
+        Application::on_start(&self.fallback, state)?;
+
+        for app in self.routes.values() {
+            app.on_start(state)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "routed-application"
+    }
+
+    fn unordered_execution(&self, state: &S, request: Request<Self, S>) -> Reply<Self, S> {
+        self.route(request.namespace())
+            .unordered_execution(state, request)
+    }
+
+    fn unordered_batched_execution(
+        &self,
+        state: &S,
+        requests: UnorderedBatch<Request<Self, S>>,
+    ) -> BatchReplies<Reply<Self, S>> {
+        let mut reply_batch = BatchReplies::with_capacity(requests.len());
+
+        for (route_key, run) in
+            requests.split_into_runs(|update| self.route_key(update.operation().namespace()))
+        {
+            reply_batch.extend_from(
+                self.target(route_key)
+                    .unordered_batched_execution(state, run),
+            );
+        }
+
+        reply_batch
+    }
+
+    fn read(&self, state: &S, from: NodeId) -> Option<Reply<Self, S>> {
+        Application::read(&self.fallback, state, from)
+    }
+
+    fn update(&self, state: &mut S, request: Request<Self, S>) -> Reply<Self, S> {
+        self.route(request.namespace()).update(state, request)
+    }
+
+    fn update_batch(
+        &self,
+        state: &mut S,
+        batch: UpdateBatch<Request<Self, S>>,
+    ) -> BatchReplies<Reply<Self, S>> {
+        let seq = batch.sequence_number();
+        let mut reply_batch = BatchReplies::with_capacity(batch.len());
+
+        let targets = self.targets_of(&batch);
+
+        targets.iter().for_each(|app| app.pre_batch(state, seq));
+
+        for update in batch.into_inner() {
+            let (peer_id, sess, opid, req) = update.into_inner();
+            let app = self.route(req.namespace());
+
+            let reply = if app.wants_request_timing() {
+                let start = Instant::now();
+                let reply = app.update(state, req);
+                app.on_request_executed(opid, start.elapsed());
+
+                reply
+            } else {
+                app.update(state, req)
+            };
+
+            reply_batch.add(peer_id, sess, opid, reply);
+        }
+
+        targets.iter().for_each(|app| app.post_batch(state, seq));
+
+        reply_batch
+    }
+
+    fn try_update(&self, state: &mut S, request: Request<Self, S>) -> Result<Reply<Self, S>> {
+        self.route(request.namespace()).try_update(state, request)
+    }
+
+    fn try_update_batch(
+        &self,
+        state: &mut S,
+        batch: UpdateBatch<Request<Self, S>>,
+    ) -> BatchReplies<ReplyOutcome<Reply<Self, S>>> {
+        let seq = batch.sequence_number();
+        let mut reply_batch = BatchReplies::with_capacity(batch.len());
+
+        let targets = self.targets_of(&batch);
+
+        targets.iter().for_each(|app| app.pre_batch(state, seq));
+
+        for update in batch.into_inner() {
+            let (peer_id, sess, opid, req) = update.into_inner();
+            let reply = self.route(req.namespace()).try_update(state, req);
+
+            reply_batch.add_result(peer_id, sess, opid, reply.into());
+        }
+
+        targets.iter().for_each(|app| app.post_batch(state, seq));
+
+        reply_batch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use atlas_common::error::*;
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+
+    use crate::app::{Application, BatchReplies, UnorderedBatch, UpdateBatch};
+    use crate::serialize::ApplicationData;
+
+    use super::{NamespacedRequest, RoutedApplication};
+
+    /// Requests are `(namespace, value)` pairs, replies name the application which served them
+    struct RoutedData;
+
+    impl NamespacedRequest for (String, u64) {
+        fn namespace(&self) -> &str {
+            &self.0
+        }
+    }
+
+    fn write_string<W: Write>(mut w: W, string: &str) -> Result<()> {
+        w.write_all(&(string.len() as u64).to_le_bytes())?;
+
+        Ok(w.write_all(string.as_bytes())?)
+    }
+
+    fn read_u64<R: Read>(mut r: R) -> Result<u64> {
+        let mut bytes = [0; 8];
+        r.read_exact(&mut bytes)?;
+
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn read_string<R: Read>(mut r: R) -> Result<String> {
+        let mut bytes = vec![0; read_u64(&mut r)? as usize];
+        r.read_exact(&mut bytes)?;
+
+        Ok(String::from_utf8(bytes)?)
+    }
+
+    impl ApplicationData for RoutedData {
+        type Request = (String, u64);
+        type Reply = String;
+
+        fn serialize_request<W>(mut w: W, request: &Self::Request) -> Result<()>
+        where
+            W: Write,
+        {
+            write_string(&mut w, &request.0)?;
+
+            Ok(w.write_all(&request.1.to_le_bytes())?)
+        }
+
+        fn deserialize_request<R>(mut r: R) -> Result<Self::Request>
+        where
+            R: Read,
+        {
+            Ok((read_string(&mut r)?, read_u64(&mut r)?))
+        }
+
+        fn serialize_reply<W>(w: W, reply: &Self::Reply) -> Result<()>
+        where
+            W: Write,
+        {
+            write_string(w, reply)
+        }
+
+        fn deserialize_reply<R>(r: R) -> Result<Self::Reply>
+        where
+            R: Read,
+        {
+            read_string(r)
+        }
+    }
+
+    /// Records everything it executes (and its hooks) in the shared state, tagged with its name.
+    /// Requests with the value 0 are rejected by `try_update()`.
+    struct RecordingApp(&'static str);
+
+    impl Application<Vec<String>> for RecordingApp {
+        type AppData = RoutedData;
+
+        fn initial_state() -> Result<Vec<String>> {
+            Ok(vec![String::from("initial")])
+        }
+
+        fn on_start(&self, state: &mut Vec<String>) -> Result<()> {
+            state.push(format!("{}:start", self.0));
+
+            Ok(())
+        }
+
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn unordered_execution(&self, _state: &Vec<String>, request: (String, u64)) -> String {
+            format!("{}:{}", self.0, request.1)
+        }
+
+        fn read(&self, _state: &Vec<String>, _from: NodeId) -> Option<String> {
+            Some(format!("{}:read", self.0))
+        }
+
+        fn update(&self, state: &mut Vec<String>, request: (String, u64)) -> String {
+            let executed = format!("{}:{}", self.0, request.1);
+
+            state.push(executed.clone());
+
+            executed
+        }
+
+        fn pre_batch(&self, state: &mut Vec<String>, _seq: SeqNo) {
+            state.push(format!("{}:pre", self.0));
+        }
+
+        fn post_batch(&self, state: &mut Vec<String>, _seq: SeqNo) {
+            state.push(format!("{}:post", self.0));
+        }
+
+        fn try_update(&self, state: &mut Vec<String>, request: (String, u64)) -> Result<String> {
+            if request.1 == 0 {
+                return Err(anyhow::anyhow!("{} rejects 0", self.0));
+            }
+
+            Ok(self.update(state, request))
+        }
+    }
+
+    fn routed() -> RoutedApplication<Vec<String>, RecordingApp> {
+        RoutedApplication::new(RecordingApp("fallback"))
+            .with_route("log", RecordingApp("log"))
+            .with_route("counter", RecordingApp("counter"))
+    }
+
+    fn request(namespace: &str, value: u64) -> (String, u64) {
+        (String::from(namespace), value)
+    }
+
+    fn mixed_batch() -> UpdateBatch<(String, u64)> {
+        let mut batch = UpdateBatch::new(SeqNo::ONE);
+
+        let requests = [
+            ("counter", 1),
+            ("counter", 2),
+            ("log", 3),
+            ("other", 4),
+            ("counter", 5),
+        ];
+
+        for (op, (namespace, value)) in requests.into_iter().enumerate() {
+            batch.add(
+                NodeId(1),
+                SeqNo::ZERO,
+                SeqNo::from(op as u32),
+                request(namespace, value),
+            );
+        }
+
+        batch
+    }
+
+    fn payloads(replies: BatchReplies<String>) -> Vec<String> {
+        replies
+            .into_inner()
+            .into_iter()
+            .map(|reply| reply.into_inner().3)
+            .collect()
+    }
+
+    #[test]
+    fn mixed_batch_hits_the_sub_application_of_each_namespace() {
+        let app = routed();
+        let mut state = Vec::new();
+
+        let replies = app.update_batch(&mut state, mixed_batch());
+
+        assert_eq!(
+            replies
+                .inner()
+                .iter()
+                .map(|reply| reply.operation_id)
+                .collect::<Vec<_>>(),
+            (0..5u32).map(SeqNo::from).collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            payloads(replies),
+            ["counter:1", "counter:2", "log:3", "fallback:4", "counter:5"]
+        );
+
+        // The hooks of each sub-application run once, around all of its requests
+        assert_eq!(
+            state,
+            [
+                "counter:pre",
+                "log:pre",
+                "fallback:pre",
+                "counter:1",
+                "counter:2",
+                "log:3",
+                "fallback:4",
+                "counter:5",
+                "counter:post",
+                "log:post",
+                "fallback:post",
+            ]
+        );
+    }
+
+    #[test]
+    fn unordered_batches_are_routed_in_order() {
+        let app = routed();
+        let mut requests = UnorderedBatch::new();
+
+        for (op, namespace) in ["log", "missing", "log", "counter"].into_iter().enumerate() {
+            requests.add(
+                NodeId(1),
+                SeqNo::ZERO,
+                SeqNo::from(op as u32),
+                request(namespace, op as u64),
+            );
+        }
+
+        let replies = app.unordered_batched_execution(&Vec::new(), requests);
+
+        assert_eq!(
+            payloads(replies),
+            ["log:0", "fallback:1", "log:2", "counter:3"]
+        );
+    }
+
+    #[test]
+    fn try_update_is_routed_by_namespace() {
+        let app = routed();
+        let mut state = Vec::new();
+
+        assert_eq!(
+            app.try_update(&mut state, request("log", 7)).unwrap(),
+            "log:7"
+        );
+
+        let error = app
+            .try_update(&mut state, request("counter", 0))
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "counter rejects 0");
+        assert_eq!(state, ["log:7"]);
+    }
+
+    #[test]
+    fn try_update_batch_runs_the_sub_application_hooks() {
+        let app = routed();
+        let mut state = Vec::new();
+
+        let mut batch = UpdateBatch::new(SeqNo::ONE);
+        batch.add(NodeId(1), SeqNo::ZERO, SeqNo::ZERO, request("log", 0));
+        batch.add(NodeId(1), SeqNo::ZERO, SeqNo::ONE, request("log", 1));
+
+        let replies = app.try_update_batch(&mut state, batch);
+
+        assert_eq!(replies.errors().count(), 1);
+        assert_eq!(state, ["log:pre", "log:1", "log:post"]);
+    }
+
+    #[test]
+    fn reads_and_initial_state_come_from_the_fallback() {
+        let app = routed();
+
+        assert_eq!(
+            RoutedApplication::<Vec<String>, RecordingApp>::initial_state().unwrap(),
+            ["initial"]
+        );
+        assert_eq!(
+            app.read(&Vec::new(), NodeId(3)),
+            Some(String::from("fallback:read"))
+        );
+    }
+
+    #[test]
+    fn on_start_starts_every_sub_application() {
+        let app = routed();
+        let mut state = Vec::new();
+
+        app.on_start(&mut state).unwrap();
+
+        assert_eq!(state, ["fallback:start", "counter:start", "log:start"]);
+    }
+
+    #[test]
+    fn unknown_namespaces_are_served_by_the_fallback() {
+        let app = routed();
+
+        assert_eq!(app.route("counter").name(), "counter");
+        assert_eq!(app.route("missing").name(), "fallback");
+        assert_eq!(app.namespaces().collect::<Vec<_>>(), ["counter", "log"]);
+    }
+}
@@ -1,19 +1,27 @@
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 
-use anyhow::Context;
+use thiserror::Error;
 
+use atlas_common::channel::mixed::ChannelMixedTx;
+use atlas_common::channel::new_oneshot_channel;
+use atlas_common::channel::oneshot::{OneShotRx, OneShotTx};
 use atlas_common::channel::sync::ChannelSyncTx;
-use atlas_common::error::*;
+use atlas_common::channel::TrySendReturnError;
 use atlas_common::maybe_vec::MaybeVec;
 use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
 
-use crate::app::{UnorderedBatch, UpdateBatch};
+use crate::app::{BatchPriority, BatchReplies, UnorderedBatch, UpdateBatch};
 
 pub mod app;
 pub mod serialize;
 pub mod state;
+#[cfg(feature = "testing")]
+pub mod testing;
 
-pub enum ExecutionRequest<O> {
+pub enum ExecutionRequest<O, P = ()> {
     // Poll the state channel
     // As we have an incoming state update
     PollStateChannel,
@@ -24,71 +32,2246 @@ pub enum ExecutionRequest<O> {
 
     // update the state of the service
     Update((UpdateBatch<O>, Instant)),
+    // same as update, but for a group of batches which must be executed
+    // back to back, in the given order
+    UpdateMany((MaybeVec<UpdateBatch<O>>, Instant)),
     // same as above, and include the application state
     // in the reply, used for local checkpoints
     UpdateAndGetAppstate((UpdateBatch<O>, Instant)),
+    // same as update, but the replies are discarded,
+    // used for fire and forget requests
+    UpdateNoReply((UpdateBatch<O>, Instant)),
+    // same as update, but the replies are sent back through the given channel,
+    // instead of through the regular reply path
+    UpdateWithReplyChannel((UpdateBatch<O>, Instant, OneShotTx<BatchReplies<P>>)),
 
     //Execute an un ordered batch of requests
     ExecuteUnordered(UnorderedBatch<O>),
 
-    // read the state of the service
+    // read the state of the service, on behalf of the given node,
+    // served by the application's read method
+    #[deprecated(note = "use `ExecutionRequest::ReadQuery` (see `ExecutionRequest::read()`)")]
     Read(NodeId),
+
+    // read the state of the service, on behalf of the node `from`,
+    // served by the application's read_query method.
+    // The query, if any, parameterizes what is read
+    ReadQuery {
+        from: NodeId,
+        query: Option<O>,
+    },
+
+    // Produce an application state checkpoint, without executing new work.
+    // The checkpoint is taken at the sequence number of the last executed batch
+    Checkpoint,
+
+    // Synchronization point, FIFO with every other request.
+    // The executor signals the channel once every request queued before it has been executed
+    Barrier(OneShotTx<()>),
+
+    // Drain and stop the executor.
+    // Any batch that has already been dequeued is finished before this is honored.
+    // If an acknowledgement channel is provided, the executor signals it once it has stopped
+    Shutdown(Option<OneShotTx<()>>),
+}
+
+/// The kind of an [`ExecutionRequest`], without its contents
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ExecutionRequestKind {
+    PollStateChannel,
+    CatchUp,
+    Update,
+    UpdateMany,
+    UpdateAndGetAppstate,
+    UpdateNoReply,
+    UpdateWithReplyChannel,
+    ExecuteUnordered,
+    Read,
+    Checkpoint,
+    Barrier,
+    Shutdown,
+}
+
+impl<O, P> ExecutionRequest<O, P> {
+    /// The kind of this request, useful to report metrics for each dequeued request
+    #[allow(deprecated)]
+    pub fn kind(&self) -> ExecutionRequestKind {
+        match self {
+            ExecutionRequest::PollStateChannel => ExecutionRequestKind::PollStateChannel,
+            ExecutionRequest::CatchUp(_) => ExecutionRequestKind::CatchUp,
+            ExecutionRequest::Update(_) => ExecutionRequestKind::Update,
+            ExecutionRequest::UpdateMany(_) => ExecutionRequestKind::UpdateMany,
+            ExecutionRequest::UpdateAndGetAppstate(_) => ExecutionRequestKind::UpdateAndGetAppstate,
+            ExecutionRequest::UpdateNoReply(_) => ExecutionRequestKind::UpdateNoReply,
+            ExecutionRequest::UpdateWithReplyChannel(_) => {
+                ExecutionRequestKind::UpdateWithReplyChannel
+            }
+            ExecutionRequest::ExecuteUnordered(_) => ExecutionRequestKind::ExecuteUnordered,
+            ExecutionRequest::Read(_) | ExecutionRequest::ReadQuery { .. } => {
+                ExecutionRequestKind::Read
+            }
+            ExecutionRequest::Checkpoint => ExecutionRequestKind::Checkpoint,
+            ExecutionRequest::Barrier(_) => ExecutionRequestKind::Barrier,
+            ExecutionRequest::Shutdown(_) => ExecutionRequestKind::Shutdown,
+        }
+    }
+
+    /// A read of the state on behalf of `from`, without a query.
+    pub fn read(from: NodeId) -> Self {
+        ExecutionRequest::ReadQuery { from, query: None }
+    }
+
+    /// A read of the state on behalf of `from`, parameterized by `query`.
+    pub fn read_query(from: NodeId, query: O) -> Self {
+        ExecutionRequest::ReadQuery {
+            from,
+            query: Some(query),
+        }
+    }
+
+    /// The priority of the ordered batch carried by this request, if any.
+    pub fn priority(&self) -> Option<BatchPriority> {
+        match self {
+            ExecutionRequest::Update((batch, _))
+            | ExecutionRequest::UpdateAndGetAppstate((batch, _))
+            | ExecutionRequest::UpdateNoReply((batch, _))
+            | ExecutionRequest::UpdateWithReplyChannel((batch, _, _)) => Some(batch.priority()),
+            _ => None,
+        }
+    }
+}
+
+/// Errors produced by the executor handle
+#[derive(Error, Debug)]
+pub enum ExecutorError {
+    #[error("{context}: the executor channel is closed")]
+    ChannelClosed { context: &'static str },
+    #[error("The executor channel was still full after {attempts} attempts")]
+    ChannelFull { attempts: usize },
+    #[error("The executor is shutting down and no longer accepts requests")]
+    ShutdownInProgress,
+    #[error("The batch holds {len} requests, above the maximum of {max}")]
+    BatchTooLarge { len: usize, max: usize },
+    #[error("Catch up requests must be split into chunks of at least one batch")]
+    InvalidChunkSize,
+}
+
+/// The result of the operations of the executor handle.
+///
+/// [`ExecutorError`] converts into the crate wide error type, so these can still be
+/// propagated with `?` where no specific handling is needed.
+pub type ExecutorResult<T> = std::result::Result<T, ExecutorError>;
+
+/// Best effort count of the requests of each kind that are queued in the executor channel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PendingKindCounts {
+    pub poll_state_channel: usize,
+    pub catch_up: usize,
+    pub update: usize,
+    pub update_many: usize,
+    pub update_and_get_appstate: usize,
+    pub update_no_reply: usize,
+    pub update_with_reply_channel: usize,
+    pub execute_unordered: usize,
+    pub read: usize,
+    pub checkpoint: usize,
+    pub barrier: usize,
+    pub shutdown: usize,
+}
+
+/// Counters of the requests of each kind that are queued in the executor channel.
+///
+/// These are incremented by the [`ExecutorHandle`] when a request is queued, and
+/// must be decremented by the executor (with [`PendingRequestCounters::dequeued()`])
+/// when it consumes one.
+#[derive(Default)]
+pub struct PendingRequestCounters {
+    poll_state_channel: AtomicUsize,
+    catch_up: AtomicUsize,
+    update: AtomicUsize,
+    update_many: AtomicUsize,
+    update_and_get_appstate: AtomicUsize,
+    update_no_reply: AtomicUsize,
+    update_with_reply_channel: AtomicUsize,
+    execute_unordered: AtomicUsize,
+    read: AtomicUsize,
+    checkpoint: AtomicUsize,
+    barrier: AtomicUsize,
+    shutdown: AtomicUsize,
+}
+
+impl PendingRequestCounters {
+    fn counter_for<O, P>(&self, request: &ExecutionRequest<O, P>) -> &AtomicUsize {
+        match request.kind() {
+            ExecutionRequestKind::PollStateChannel => &self.poll_state_channel,
+            ExecutionRequestKind::CatchUp => &self.catch_up,
+            ExecutionRequestKind::Update => &self.update,
+            ExecutionRequestKind::UpdateMany => &self.update_many,
+            ExecutionRequestKind::UpdateAndGetAppstate => &self.update_and_get_appstate,
+            ExecutionRequestKind::UpdateNoReply => &self.update_no_reply,
+            ExecutionRequestKind::UpdateWithReplyChannel => &self.update_with_reply_channel,
+            ExecutionRequestKind::ExecuteUnordered => &self.execute_unordered,
+            ExecutionRequestKind::Read => &self.read,
+            ExecutionRequestKind::Checkpoint => &self.checkpoint,
+            ExecutionRequestKind::Barrier => &self.barrier,
+            ExecutionRequestKind::Shutdown => &self.shutdown,
+        }
+    }
+
+    /// Marks the given request as having been consumed by the executor.
+    ///
+    /// Counters never go below zero.
+    pub fn dequeued<O, P>(&self, request: &ExecutionRequest<O, P>) {
+        decrement_saturating(self.counter_for(request));
+    }
+
+    /// A snapshot of the current counts, which may already be stale when read.
+    pub fn snapshot(&self) -> PendingKindCounts {
+        PendingKindCounts {
+            poll_state_channel: self.poll_state_channel.load(Ordering::Relaxed),
+            catch_up: self.catch_up.load(Ordering::Relaxed),
+            update: self.update.load(Ordering::Relaxed),
+            update_many: self.update_many.load(Ordering::Relaxed),
+            update_and_get_appstate: self.update_and_get_appstate.load(Ordering::Relaxed),
+            update_no_reply: self.update_no_reply.load(Ordering::Relaxed),
+            update_with_reply_channel: self.update_with_reply_channel.load(Ordering::Relaxed),
+            execute_unordered: self.execute_unordered.load(Ordering::Relaxed),
+            read: self.read.load(Ordering::Relaxed),
+            checkpoint: self.checkpoint.load(Ordering::Relaxed),
+            barrier: self.barrier.load(Ordering::Relaxed),
+            shutdown: self.shutdown.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn decrement_saturating(counter: &AtomicUsize) {
+    let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+        count.checked_sub(1)
+    });
+}
+
+/// Counters of the work executed so far by the executor.
+///
+/// Shared between the [`ExecutorHandle`] (see [`ExecutorHandle::with_stats()`])
+/// and the execution loop, which records the work it executes.
+#[derive(Default)]
+pub struct ExecutorStats {
+    ordered_executed: AtomicU64,
+    unordered_executed: AtomicU64,
+}
+
+impl ExecutorStats {
+    /// Records that `count` ordered requests have been executed
+    pub fn record_ordered(&self, count: u64) {
+        self.ordered_executed.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Records that `count` unordered requests have been executed
+    pub fn record_unordered(&self, count: u64) {
+        self.unordered_executed.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn ordered_executed(&self) -> u64 {
+        self.ordered_executed.load(Ordering::Relaxed)
+    }
+
+    pub fn unordered_executed(&self) -> u64 {
+        self.unordered_executed.load(Ordering::Relaxed)
+    }
+}
+
+/// State that is shared by all clones of an [`ExecutorHandle`]
+struct ExecutorHandleShared {
+    shutdown: AtomicBool,
+    /// The sequence number of the greatest ordered batch queued, plus one,
+    /// so that 0 marks that no ordered batch has been queued yet
+    last_queued_seq: AtomicU64,
+}
+
+impl ExecutorHandleShared {
+    fn new() -> Self {
+        ExecutorHandleShared {
+            shutdown: AtomicBool::new(false),
+            last_queued_seq: AtomicU64::new(0),
+        }
+    }
+
+    fn ensure_running(&self) -> ExecutorResult<()> {
+        if self.shutdown.load(Ordering::Acquire) {
+            return Err(ExecutorError::ShutdownInProgress);
+        }
+
+        Ok(())
+    }
+
+    /// Marks the executor as shutting down, so every further request is rejected,
+    /// failing if a shutdown was already requested.
+    fn begin_shutdown(&self) -> ExecutorResult<()> {
+        if self.shutdown.swap(true, Ordering::AcqRel) {
+            return Err(ExecutorError::ShutdownInProgress);
+        }
+
+        Ok(())
+    }
+
+    /// Reverts `begin_shutdown()`, for when the shutdown order could not be sent,
+    /// as the executor would otherwise keep running while rejecting every request.
+    fn abort_shutdown(&self) {
+        self.shutdown.store(false, Ordering::Release);
+    }
+
+    fn last_queued_seq(&self) -> Option<SeqNo> {
+        self.last_queued_seq
+            .load(Ordering::Acquire)
+            .checked_sub(1)
+            .map(|seq| SeqNo::from(seq as u32))
+    }
+
+    /// Asserts, in debug builds, that ordered batches with the sequence numbers `seqs` may be
+    /// queued (in this order, as a single request) after the ones that were already queued.
+    ///
+    /// The executor must receive ordered batches in strictly increasing sequence number order.
+    /// The only exception are the chunks of a batch (see [`UpdateBatch::split_into_chunks()`]),
+    /// which share its sequence number, so they must be queued together with `queue_update_many()`,
+    /// within which consecutive batches may share a sequence number.
+    ///
+    /// This runs before the request is sent, so an out of order batch never reaches the executor.
+    fn check_queued_seqs(&self, seqs: &[SeqNo]) {
+        if let (Some(last), Some(first)) = (self.last_queued_seq(), seqs.first()) {
+            debug_assert!(
+                *first > last,
+                "Ordered batch {:?} queued after batch {:?}, breaking sequence number order",
+                first,
+                last
+            );
+        }
+
+        for pair in seqs.windows(2) {
+            debug_assert!(
+                pair[1] >= pair[0],
+                "Ordered batch {:?} queued after batch {:?}, breaking sequence number order",
+                pair[1],
+                pair[0]
+            );
+        }
+    }
+
+    /// Records `seq` as the last queued sequence number, once its batch has been sent.
+    fn record_queued_seq(&self, seq: SeqNo) {
+        self.last_queued_seq
+            .fetch_max(u64::from(u32::from(seq)) + 1, Ordering::AcqRel);
+    }
+}
+
+fn check_batch_len<RQ>(
+    max_batch_len: Option<usize>,
+    batch: &UpdateBatch<RQ>,
+) -> ExecutorResult<()> {
+    match max_batch_len {
+        Some(max) if batch.len() > max => Err(ExecutorError::BatchTooLarge {
+            len: batch.len(),
+            max,
+        }),
+        _ => Ok(()),
+    }
 }
 
 /// Represents a handle to the client request executor.
-pub struct ExecutorHandle<RQ> {
-    e_tx: ChannelSyncTx<ExecutionRequest<RQ>>,
+///
+/// `RP` is the type of the replies that can be sent back directly to the caller
+/// (see [`ExecutorHandle::queue_update_blocking()`]).
+pub struct ExecutorHandle<RQ, RP = ()> {
+    e_tx: ChannelSyncTx<ExecutionRequest<RQ, RP>>,
+    shared: Arc<ExecutorHandleShared>,
+    pending: Arc<PendingRequestCounters>,
+    stats: Option<Arc<ExecutorStats>>,
+    max_batch_len: Option<usize>,
 }
 
-impl<RQ> ExecutorHandle<RQ> {
-    pub fn new(tx: ChannelSyncTx<ExecutionRequest<RQ>>) -> Self {
-        ExecutorHandle { e_tx: tx }
+impl<RQ, RP> ExecutorHandle<RQ, RP> {
+    pub fn new(tx: ChannelSyncTx<ExecutionRequest<RQ, RP>>) -> Self {
+        ExecutorHandle {
+            e_tx: tx,
+            shared: Arc::new(ExecutorHandleShared::new()),
+            pending: Arc::new(PendingRequestCounters::default()),
+            stats: None,
+            max_batch_len: None,
+        }
+    }
+
+    /// Same as `new()`, additionally exposing the execution counters in `stats`,
+    /// which must be shared with the execution loop so it can record its work.
+    ///
+    /// Handles created with `new()` have no stats, and report no executed work.
+    pub fn with_stats(
+        tx: ChannelSyncTx<ExecutionRequest<RQ, RP>>,
+        stats: Arc<ExecutorStats>,
+    ) -> Self {
+        ExecutorHandle {
+            stats: Some(stats),
+            ..Self::new(tx)
+        }
+    }
+
+    /// Rejects ordered batches holding more than `max` requests with
+    /// [`ExecutorError::BatchTooLarge`], instead of queueing them.
+    pub fn with_max_batch_len(mut self, max: usize) -> Self {
+        self.max_batch_len = Some(max);
+        self
+    }
+
+    fn check_batch_len(&self, batch: &UpdateBatch<RQ>) -> ExecutorResult<()> {
+        check_batch_len(self.max_batch_len, batch)
+    }
+
+    /// An async handle to the same executor, which sends its requests through `tx`.
+    ///
+    /// `tx` must feed the same execution loop as this handle. The async handle shares the
+    /// shutdown flag, the pending request counters, the batch length limit and the
+    /// sequence number tracking of this handle (and all of its clones), so it is subject to
+    /// the same checks.
+    pub fn to_async(
+        &self,
+        tx: ChannelMixedTx<ExecutionRequest<RQ, RP>>,
+    ) -> ExecutorHandleAsync<RQ, RP> {
+        ExecutorHandleAsync {
+            e_tx: tx,
+            shared: self.shared.clone(),
+            pending: self.pending.clone(),
+            max_batch_len: self.max_batch_len,
+        }
+    }
+
+    /// The amount of ordered requests executed so far (0 if this handle has no stats).
+    pub fn ordered_executed(&self) -> u64 {
+        self.stats
+            .as_ref()
+            .map_or(0, |stats| stats.ordered_executed())
+    }
+
+    /// The amount of unordered requests executed so far (0 if this handle has no stats).
+    pub fn unordered_executed(&self) -> u64 {
+        self.stats
+            .as_ref()
+            .map_or(0, |stats| stats.unordered_executed())
+    }
+
+    fn send_request(
+        &self,
+        request: ExecutionRequest<RQ, RP>,
+        context: &'static str,
+    ) -> ExecutorResult<()> {
+        let counter = self.pending.counter_for(&request);
+
+        // Count before sending, so the executor can never observe the request
+        // before it has been counted
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        let result = self
+            .e_tx
+            .send(request)
+            .map_err(|_| ExecutorError::ChannelClosed { context });
+
+        if result.is_err() {
+            decrement_saturating(counter);
+        }
+
+        result
+    }
+
+    /// The sequence number of the last ordered batch queued through this handle
+    /// (or any of its clones), if any.
+    pub fn last_queued_seq(&self) -> Option<SeqNo> {
+        self.shared.last_queued_seq()
+    }
+
+    fn record_queued_seq(&self, seq: SeqNo) {
+        self.shared.record_queued_seq(seq)
+    }
+
+    /// Whether this is the last live clone of this handle, meaning the executor
+    /// will observe every handle as dropped once this one goes.
+    pub fn is_last(&self) -> bool {
+        Arc::strong_count(&self.shared) == 1
+    }
+
+    /// A token with which the execution loop can observe whether every clone of this
+    /// handle has been dropped, so it can terminate cleanly.
+    ///
+    /// The token does not keep the handles alive.
+    pub fn liveness(&self) -> HandleLiveness {
+        HandleLiveness {
+            shared: Arc::downgrade(&self.shared),
+        }
+    }
+
+    /// Whether a shutdown has already been requested through this handle (or any of its clones).
+    pub fn is_shutting_down(&self) -> bool {
+        self.shared.shutdown.load(Ordering::Acquire)
+    }
+
+    fn ensure_running(&self) -> ExecutorResult<()> {
+        self.shared.ensure_running()
+    }
+
+    /// Requests the executor to drain and stop.
+    ///
+    /// After this is called, every other `queue_*` call fails with [`ExecutorError::ShutdownInProgress`].
+    pub fn shutdown(&self) -> ExecutorResult<()> {
+        self.send_shutdown(None)
+    }
+
+    /// Same as `shutdown()`, but blocks until the executor acknowledges it has stopped.
+    pub fn shutdown_and_wait(self) -> ExecutorResult<()> {
+        let (tx, rx) = new_oneshot_channel();
+
+        self.send_shutdown(Some(tx))?;
+
+        rx.recv().map_err(|_| ExecutorError::ChannelClosed {
+            context: "Failed to receive shutdown acknowledgement from the executor",
+        })
+    }
+
+    fn send_shutdown(&self, ack: Option<OneShotTx<()>>) -> ExecutorResult<()> {
+        self.shared.begin_shutdown()?;
+
+        let result = self.send_request(
+            ExecutionRequest::Shutdown(ack),
+            "Failed to place shutdown order into executor channel",
+        );
+
+        if result.is_err() {
+            self.shared.abort_shutdown();
+        }
+
+        result
+    }
+
+    /// The amount of execution requests that are queued but have not yet
+    /// been consumed by the executor.
+    ///
+    /// This is only a snapshot and may already be stale by the time it is read,
+    /// as other handles can concurrently send and the executor concurrently consume.
+    pub fn pending_len(&self) -> usize {
+        self.e_tx.len()
+    }
+
+    /// The maximum amount of execution requests the executor channel can hold,
+    /// or `None` if the channel is unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.e_tx.capacity()
+    }
+
+    /// A best effort count of the queued requests of each kind.
+    ///
+    /// Only accurate if the executor reports the requests it consumes through the
+    /// counters obtained with `pending_counters()`.
+    pub fn pending_kinds(&self) -> PendingKindCounts {
+        self.pending.snapshot()
+    }
+
+    /// The counters of queued requests, to be handed to the executor so it can
+    /// report the requests it consumes.
+    pub fn pending_counters(&self) -> Arc<PendingRequestCounters> {
+        self.pending.clone()
     }
 
     /// Sets the current state of the execution layer to the given value.
-    pub fn poll_state_channel(&self) -> Result<()> {
-        self.e_tx
-            .send(ExecutionRequest::PollStateChannel)
-            .context("Failed to place poll order into executor channel")
+    pub fn poll_state_channel(&self) -> ExecutorResult<()> {
+        self.ensure_running()?;
+
+        self.send_request(
+            ExecutionRequest::PollStateChannel,
+            "Failed to place poll order into executor channel",
+        )
+    }
+
+    pub fn catch_up_to_quorum(&self, requests: MaybeVec<UpdateBatch<RQ>>) -> ExecutorResult<()> {
+        self.ensure_running()?;
+
+        self.send_request(
+            ExecutionRequest::CatchUp(requests),
+            "Failed to place catch up order into executor channel",
+        )
     }
 
-    pub fn catch_up_to_quorum(&self, requests: MaybeVec<UpdateBatch<RQ>>) -> Result<()> {
-        self.e_tx
-            .send(ExecutionRequest::CatchUp(requests))
-            .context("Failed to place catch up order into executor channel")
+    /// Same as `catch_up_to_quorum()`, but splits the batches into several catch up
+    /// requests of at most `chunk` batches each.
+    ///
+    /// Each request is only sent once the channel has room for it, so the executor
+    /// applies back pressure between chunks. The order of the batches is preserved.
+    /// Like `catch_up_to_quorum()`, an empty `requests` is still sent as a single
+    /// (empty) catch up request.
+    ///
+    /// Fails with [`ExecutorError::InvalidChunkSize`] if `chunk` is 0.
+    pub fn catch_up_chunked(
+        &self,
+        requests: MaybeVec<UpdateBatch<RQ>>,
+        chunk: usize,
+    ) -> ExecutorResult<()> {
+        if chunk == 0 {
+            return Err(ExecutorError::InvalidChunkSize);
+        }
+
+        let mut batches = requests.into_iter().peekable();
+
+        if batches.peek().is_none() {
+            return self.catch_up_to_quorum(MaybeVec::from_many(Vec::new()));
+        }
+
+        while batches.peek().is_some() {
+            let chunk_batches: Vec<_> = batches.by_ref().take(chunk).collect();
+
+            self.catch_up_to_quorum(MaybeVec::from_many(chunk_batches))?;
+        }
+
+        Ok(())
     }
 
     /// Queues a batch of requests `batch` for execution.
-    pub fn queue_update(&self, batch: UpdateBatch<RQ>) -> Result<()> {
-        self.e_tx
-            .send(ExecutionRequest::Update((batch, Instant::now())))
-            .context("Failed to place update order into executor channel")
+    pub fn queue_update(&self, batch: UpdateBatch<RQ>) -> ExecutorResult<()> {
+        self.ensure_running()?;
+        self.check_batch_len(&batch)?;
+
+        let (batch, now) = stamp_batch(batch);
+
+        let seq = batch.sequence_number();
+
+        self.shared.check_queued_seqs(&[seq]);
+
+        self.send_request(
+            ExecutionRequest::Update((batch, now)),
+            "Failed to place update order into executor channel",
+        )?;
+
+        self.record_queued_seq(seq);
+
+        Ok(())
+    }
+
+    /// Queues several batches for execution, as a single request.
+    ///
+    /// The batches are executed back to back, in the given order, so no request
+    /// queued by another sender can be interleaved between them. Each of them is
+    /// executed (and replied to) as if it had been queued with `queue_update()`.
+    pub fn queue_update_many(&self, batches: Vec<UpdateBatch<RQ>>) -> ExecutorResult<()> {
+        self.ensure_running()?;
+
+        for batch in &batches {
+            self.check_batch_len(batch)?;
+        }
+
+        let now = Instant::now();
+        let seqs: Vec<_> = batches.iter().map(UpdateBatch::sequence_number).collect();
+
+        self.shared.check_queued_seqs(&seqs);
+
+        let batches = batches
+            .into_iter()
+            .map(|mut batch| {
+                batch.stamp_proposed_at(now);
+
+                batch
+            })
+            .collect();
+
+        self.send_request(
+            ExecutionRequest::UpdateMany((MaybeVec::from_many(batches), now)),
+            "Failed to place update many order into executor channel",
+        )?;
+
+        for seq in seqs {
+            self.record_queued_seq(seq);
+        }
+
+        Ok(())
+    }
+
+    /// Queues a batch of requests `batch` for execution, discarding their replies.
+    ///
+    /// Useful for fire and forget requests, whose replies nobody waits on.
+    pub fn queue_update_no_reply(&self, batch: UpdateBatch<RQ>) -> ExecutorResult<()> {
+        self.ensure_running()?;
+        self.check_batch_len(&batch)?;
+
+        let (batch, now) = stamp_batch(batch);
+
+        let seq = batch.sequence_number();
+
+        self.shared.check_queued_seqs(&[seq]);
+
+        self.send_request(
+            ExecutionRequest::UpdateNoReply((batch, now)),
+            "Failed to place update with no reply order into executor channel",
+        )?;
+
+        self.record_queued_seq(seq);
+
+        Ok(())
+    }
+
+    /// Queues a batch of requests `batch` for execution and blocks until the
+    /// executor sends back their replies.
+    ///
+    /// The replies are delivered directly to the caller instead of through the regular
+    /// reply path. Note that this serializes the caller against the execution latency
+    /// of the batch (and of every request queued before it).
+    pub fn queue_update_blocking(
+        &self,
+        batch: UpdateBatch<RQ>,
+    ) -> ExecutorResult<BatchReplies<RP>> {
+        self.ensure_running()?;
+        self.check_batch_len(&batch)?;
+
+        let (batch, now) = stamp_batch(batch);
+        let (reply_tx, reply_rx) = new_oneshot_channel();
+
+        let seq = batch.sequence_number();
+
+        self.shared.check_queued_seqs(&[seq]);
+
+        self.send_request(
+            ExecutionRequest::UpdateWithReplyChannel((batch, now, reply_tx)),
+            "Failed to place update with reply channel order into executor channel",
+        )?;
+
+        self.record_queued_seq(seq);
+
+        reply_rx.recv().map_err(|_| ExecutorError::ChannelClosed {
+            context: "Failed to receive the replies of the batch from the executor",
+        })
+    }
+
+    /// Attempts to queue a batch of requests `batch` for execution, without blocking.
+    ///
+    /// If the executor channel is full (or closed), the batch is handed back to the caller
+    /// so it can be retried or shed.
+    pub fn try_queue_update(
+        &self,
+        batch: UpdateBatch<RQ>,
+    ) -> std::result::Result<(), UpdateBatch<RQ>> {
+        self.try_send_update(batch).map_err(|rejected| rejected.0)
+    }
+
+    /// Attempts to send `batch` without blocking, handing it back on failure
+    /// along with the reason it was not queued.
+    ///
+    /// The rejected batch is boxed, to keep the happy path's result small.
+    fn try_send_update(
+        &self,
+        batch: UpdateBatch<RQ>,
+    ) -> std::result::Result<(), Box<(UpdateBatch<RQ>, ExecutorError)>> {
+        if let Err(err) = self.ensure_running() {
+            return Err(Box::new((batch, err)));
+        }
+
+        if let Err(err) = self.check_batch_len(&batch) {
+            return Err(Box::new((batch, err)));
+        }
+
+        let (batch, now) = stamp_batch(batch);
+        let seq = batch.sequence_number();
+        self.shared.check_queued_seqs(&[seq]);
+
+        let request = ExecutionRequest::Update((batch, now));
+
+        let counter = self.pending.counter_for(&request);
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        match self.e_tx.try_send(request) {
+            Ok(()) => {
+                self.record_queued_seq(seq);
+
+                Ok(())
+            }
+            Err(err) => {
+                decrement_saturating(counter);
+
+                let (request, error) = match err {
+                    TrySendReturnError::Full(request) | TrySendReturnError::Timeout(request) => {
+                        (request, ExecutorError::ChannelFull { attempts: 1 })
+                    }
+                    TrySendReturnError::Disconnected(request) => (
+                        request,
+                        ExecutorError::ChannelClosed {
+                            context: "Failed to place update order into executor channel",
+                        },
+                    ),
+                };
+
+                match request {
+                    ExecutionRequest::Update((batch, _)) => Err(Box::new((batch, error))),
+                    _ => unreachable!("The request handed back by the channel is the one we sent"),
+                }
+            }
+        }
+    }
+
+    /// Queues a batch of requests `batch` for execution, retrying up to `attempts` times
+    /// (sleeping `backoff` between each) while the executor channel is full.
+    ///
+    /// Unlike `queue_update()`, this never blocks on the channel indefinitely, failing
+    /// with [`ExecutorError::ChannelFull`] once every attempt has been exhausted.
+    /// Any other error is returned right away, without retrying. At least one attempt is
+    /// always made, even if `attempts` is 0.
+    ///
+    /// Like `try_queue_update()`, the batch is handed back on failure, along with the
+    /// reason it was not queued, so it can be retried or shed.
+    pub fn queue_update_with_retry(
+        &self,
+        batch: UpdateBatch<RQ>,
+        attempts: usize,
+        backoff: Duration,
+    ) -> std::result::Result<(), Box<(UpdateBatch<RQ>, ExecutorError)>> {
+        let attempts = attempts.max(1);
+        let mut batch = batch;
+
+        for attempt in 0..attempts {
+            match self.try_send_update(batch) {
+                Ok(()) => return Ok(()),
+                Err(rejected) if matches!(rejected.1, ExecutorError::ChannelFull { .. }) => {
+                    batch = rejected.0
+                }
+                Err(rejected) => return Err(rejected),
+            }
+
+            if attempt + 1 < attempts {
+                std::thread::sleep(backoff);
+            }
+        }
+
+        Err(Box::new((batch, ExecutorError::ChannelFull { attempts })))
     }
 
     /// Queues a batch of unordered requests for execution
-    pub fn queue_update_unordered(&self, requests: UnorderedBatch<RQ>) -> Result<()> {
-        self.e_tx
-            .send(ExecutionRequest::ExecuteUnordered(requests))
-            .context("Failed to place unordered update order into executor channel")
+    pub fn queue_update_unordered(&self, requests: UnorderedBatch<RQ>) -> ExecutorResult<()> {
+        self.ensure_running()?;
+
+        self.send_request(
+            ExecutionRequest::ExecuteUnordered(requests),
+            "Failed to place unordered update order into executor channel",
+        )
+    }
+
+    /// Queues a read of the committed application state, on behalf of the node `from`.
+    pub fn queue_read(&self, from: NodeId) -> ExecutorResult<()> {
+        self.ensure_running()?;
+
+        self.send_request(
+            ExecutionRequest::read(from),
+            "Failed to place read order into executor channel",
+        )
+    }
+
+    /// Same as `queue_read()`, but the read is parameterized by `query`.
+    pub fn queue_read_query(&self, from: NodeId, query: RQ) -> ExecutorResult<()> {
+        self.ensure_running()?;
+
+        self.send_request(
+            ExecutionRequest::read_query(from, query),
+            "Failed to place read order into executor channel",
+        )
+    }
+
+    /// Requests the executor to produce an application state checkpoint, without
+    /// executing any new requests.
+    ///
+    /// The resulting app state message is tagged with the sequence number of the
+    /// last batch executed before this request is processed.
+    pub fn request_checkpoint(&self) -> ExecutorResult<()> {
+        self.ensure_running()?;
+
+        self.send_request(
+            ExecutionRequest::Checkpoint,
+            "Failed to place checkpoint order into executor channel",
+        )
+    }
+
+    /// Queues a barrier, returning a waiter that completes once the executor reaches it.
+    ///
+    /// Requests are processed in FIFO order, so by the time the barrier is reached, every
+    /// request queued before it (through this handle or any other) has been executed.
+    pub fn barrier(&self) -> ExecutorResult<BarrierWaiter> {
+        self.ensure_running()?;
+
+        let (tx, rx) = new_oneshot_channel();
+
+        self.send_request(
+            ExecutionRequest::Barrier(tx),
+            "Failed to place barrier order into executor channel",
+        )?;
+
+        Ok(BarrierWaiter { rx })
     }
 
     /// Same as `queue_update()`, additionally reporting the serialized
     /// application state.
     ///
     /// This is useful during local checkpoints.
-    pub fn queue_update_and_get_appstate(&self, batch: UpdateBatch<RQ>) -> Result<()> {
-        self.e_tx
-            .send(ExecutionRequest::UpdateAndGetAppstate((
-                batch,
-                Instant::now(),
-            )))
-            .context("Failed to place update and get appstate order into executor channel")
+    pub fn queue_update_and_get_appstate(&self, batch: UpdateBatch<RQ>) -> ExecutorResult<()> {
+        self.ensure_running()?;
+        self.check_batch_len(&batch)?;
+
+        let (batch, now) = stamp_batch(batch);
+
+        let seq = batch.sequence_number();
+
+        self.shared.check_queued_seqs(&[seq]);
+
+        self.send_request(
+            ExecutionRequest::UpdateAndGetAppstate((batch, now)),
+            "Failed to place update and get appstate order into executor channel",
+        )?;
+
+        self.record_queued_seq(seq);
+
+        Ok(())
     }
 }
 
-impl<RQ> Clone for ExecutorHandle<RQ> {
+impl<RQ, RP> Clone for ExecutorHandle<RQ, RP> {
     fn clone(&self) -> Self {
         let e_tx = self.e_tx.clone();
-        Self { e_tx }
+        let shared = self.shared.clone();
+        let pending = self.pending.clone();
+        let stats = self.stats.clone();
+        let max_batch_len = self.max_batch_len;
+
+        Self {
+            e_tx,
+            shared,
+            pending,
+            stats,
+            max_batch_len,
+        }
+    }
+}
+
+/// Observes whether the clones of an [`ExecutorHandle`] are still alive
+/// (see [`ExecutorHandle::liveness()`])
+#[derive(Clone)]
+pub struct HandleLiveness {
+    shared: Weak<ExecutorHandleShared>,
+}
+
+impl HandleLiveness {
+    /// Whether every clone of the handle has been dropped, meaning no more
+    /// requests can be queued.
+    pub fn all_dropped(&self) -> bool {
+        self.shared.strong_count() == 0
+    }
+}
+
+/// Waits on a barrier queued with [`ExecutorHandle::barrier()`]
+pub struct BarrierWaiter {
+    rx: OneShotRx<()>,
+}
+
+impl BarrierWaiter {
+    /// Blocks until the executor has executed every request queued before the barrier.
+    pub fn wait(self) -> ExecutorResult<()> {
+        self.rx.recv().map_err(|_| ExecutorError::ChannelClosed {
+            context: "Failed to receive barrier acknowledgement from the executor",
+        })
+    }
+}
+
+/// An object safe interface to an executor, so downstream code (e.g. test harnesses)
+/// can abstract over the executor implementation.
+///
+/// Mirrors the `queue_*` methods of [`ExecutorHandle`], which implements it, except for
+/// [`ExecutorHandle::queue_update_blocking()`], as its replies are typed by the handle's
+/// reply type, and [`ExecutorHandle::shutdown_and_wait()`], which consumes the handle.
+pub trait Executor<RQ> {
+    fn poll_state_channel(&self) -> ExecutorResult<()>;
+
+    fn catch_up_to_quorum(&self, requests: MaybeVec<UpdateBatch<RQ>>) -> ExecutorResult<()>;
+
+    fn catch_up_chunked(
+        &self,
+        requests: MaybeVec<UpdateBatch<RQ>>,
+        chunk: usize,
+    ) -> ExecutorResult<()>;
+
+    fn queue_update(&self, batch: UpdateBatch<RQ>) -> ExecutorResult<()>;
+
+    fn queue_update_many(&self, batches: Vec<UpdateBatch<RQ>>) -> ExecutorResult<()>;
+
+    fn queue_update_no_reply(&self, batch: UpdateBatch<RQ>) -> ExecutorResult<()>;
+
+    fn try_queue_update(&self, batch: UpdateBatch<RQ>) -> std::result::Result<(), UpdateBatch<RQ>>;
+
+    fn queue_update_with_retry(
+        &self,
+        batch: UpdateBatch<RQ>,
+        attempts: usize,
+        backoff: Duration,
+    ) -> std::result::Result<(), Box<(UpdateBatch<RQ>, ExecutorError)>>;
+
+    fn queue_update_unordered(&self, requests: UnorderedBatch<RQ>) -> ExecutorResult<()>;
+
+    fn queue_read(&self, from: NodeId) -> ExecutorResult<()>;
+
+    fn queue_read_query(&self, from: NodeId, query: RQ) -> ExecutorResult<()>;
+
+    fn request_checkpoint(&self) -> ExecutorResult<()>;
+
+    fn barrier(&self) -> ExecutorResult<BarrierWaiter>;
+
+    fn queue_update_and_get_appstate(&self, batch: UpdateBatch<RQ>) -> ExecutorResult<()>;
+
+    fn shutdown(&self) -> ExecutorResult<()>;
+}
+
+impl<RQ, RP> Executor<RQ> for ExecutorHandle<RQ, RP> {
+    fn poll_state_channel(&self) -> ExecutorResult<()> {
+        ExecutorHandle::poll_state_channel(self)
+    }
+
+    fn catch_up_to_quorum(&self, requests: MaybeVec<UpdateBatch<RQ>>) -> ExecutorResult<()> {
+        ExecutorHandle::catch_up_to_quorum(self, requests)
+    }
+
+    fn catch_up_chunked(
+        &self,
+        requests: MaybeVec<UpdateBatch<RQ>>,
+        chunk: usize,
+    ) -> ExecutorResult<()> {
+        ExecutorHandle::catch_up_chunked(self, requests, chunk)
+    }
+
+    fn queue_update(&self, batch: UpdateBatch<RQ>) -> ExecutorResult<()> {
+        ExecutorHandle::queue_update(self, batch)
+    }
+
+    fn queue_update_many(&self, batches: Vec<UpdateBatch<RQ>>) -> ExecutorResult<()> {
+        ExecutorHandle::queue_update_many(self, batches)
+    }
+
+    fn queue_update_no_reply(&self, batch: UpdateBatch<RQ>) -> ExecutorResult<()> {
+        ExecutorHandle::queue_update_no_reply(self, batch)
+    }
+
+    fn try_queue_update(&self, batch: UpdateBatch<RQ>) -> std::result::Result<(), UpdateBatch<RQ>> {
+        ExecutorHandle::try_queue_update(self, batch)
+    }
+
+    fn queue_update_with_retry(
+        &self,
+        batch: UpdateBatch<RQ>,
+        attempts: usize,
+        backoff: Duration,
+    ) -> std::result::Result<(), Box<(UpdateBatch<RQ>, ExecutorError)>> {
+        ExecutorHandle::queue_update_with_retry(self, batch, attempts, backoff)
+    }
+
+    fn queue_update_unordered(&self, requests: UnorderedBatch<RQ>) -> ExecutorResult<()> {
+        ExecutorHandle::queue_update_unordered(self, requests)
+    }
+
+    fn queue_read(&self, from: NodeId) -> ExecutorResult<()> {
+        ExecutorHandle::queue_read(self, from)
+    }
+
+    fn queue_read_query(&self, from: NodeId, query: RQ) -> ExecutorResult<()> {
+        ExecutorHandle::queue_read_query(self, from, query)
+    }
+
+    fn request_checkpoint(&self) -> ExecutorResult<()> {
+        ExecutorHandle::request_checkpoint(self)
+    }
+
+    fn barrier(&self) -> ExecutorResult<BarrierWaiter> {
+        ExecutorHandle::barrier(self)
+    }
+
+    fn queue_update_and_get_appstate(&self, batch: UpdateBatch<RQ>) -> ExecutorResult<()> {
+        ExecutorHandle::queue_update_and_get_appstate(self, batch)
+    }
+
+    fn shutdown(&self) -> ExecutorResult<()> {
+        ExecutorHandle::shutdown(self)
+    }
+}
+
+/// Stamps the batch with the instant at which it is being queued,
+/// so the application can see when it was proposed.
+fn stamp_batch<RQ>(mut batch: UpdateBatch<RQ>) -> (UpdateBatch<RQ>, Instant) {
+    let now = Instant::now();
+
+    batch.stamp_proposed_at(now);
+
+    (batch, now)
+}
+
+/// Represents an async handle to the client request executor.
+///
+/// Mirrors [`ExecutorHandle`], but awaits on channel capacity instead of
+/// blocking the calling thread, so it can be driven from an async task.
+///
+/// Every queueing method has an `_async` counterpart, except for
+/// [`ExecutorHandle::queue_update_blocking()`] and [`ExecutorHandle::shutdown_and_wait()`],
+/// which block waiting on the executor's response, and [`ExecutorHandle::try_queue_update()`]
+/// and [`ExecutorHandle::queue_update_with_retry()`], which exist to avoid blocking on a
+/// full channel, which awaiting already does.
+///
+/// Obtain it with [`ExecutorHandle::to_async()`] so it observes the shutdown of the
+/// blocking handles and reports the requests it queues in their counters.
+pub struct ExecutorHandleAsync<RQ, RP = ()> {
+    e_tx: ChannelMixedTx<ExecutionRequest<RQ, RP>>,
+    shared: Arc<ExecutorHandleShared>,
+    pending: Arc<PendingRequestCounters>,
+    max_batch_len: Option<usize>,
+}
+
+impl<RQ, RP> ExecutorHandleAsync<RQ, RP> {
+    /// A standalone async handle, which shares no state with any [`ExecutorHandle`].
+    pub fn new(tx: ChannelMixedTx<ExecutionRequest<RQ, RP>>) -> Self {
+        ExecutorHandleAsync {
+            e_tx: tx,
+            shared: Arc::new(ExecutorHandleShared::new()),
+            pending: Arc::new(PendingRequestCounters::default()),
+            max_batch_len: None,
+        }
+    }
+
+    async fn send_request(
+        &self,
+        request: ExecutionRequest<RQ, RP>,
+        context: &'static str,
+    ) -> ExecutorResult<()> {
+        let counter = self.pending.counter_for(&request);
+
+        // Count before sending, so the executor can never observe the request
+        // before it has been counted
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        let result = self
+            .e_tx
+            .send_async(request)
+            .await
+            .map_err(|_| ExecutorError::ChannelClosed { context });
+
+        if result.is_err() {
+            decrement_saturating(counter);
+        }
+
+        result
+    }
+
+    /// Async version of [`ExecutorHandle::poll_state_channel()`].
+    pub async fn poll_state_channel_async(&self) -> ExecutorResult<()> {
+        self.shared.ensure_running()?;
+
+        self.send_request(
+            ExecutionRequest::PollStateChannel,
+            "Failed to place poll order into executor channel",
+        )
+        .await
+    }
+
+    /// Async version of [`ExecutorHandle::catch_up_to_quorum()`].
+    pub async fn catch_up_to_quorum_async(
+        &self,
+        requests: MaybeVec<UpdateBatch<RQ>>,
+    ) -> ExecutorResult<()> {
+        self.shared.ensure_running()?;
+
+        self.send_request(
+            ExecutionRequest::CatchUp(requests),
+            "Failed to place catch up order into executor channel",
+        )
+        .await
+    }
+
+    /// Async version of [`ExecutorHandle::catch_up_chunked()`].
+    pub async fn catch_up_chunked_async(
+        &self,
+        requests: MaybeVec<UpdateBatch<RQ>>,
+        chunk: usize,
+    ) -> ExecutorResult<()> {
+        if chunk == 0 {
+            return Err(ExecutorError::InvalidChunkSize);
+        }
+
+        let mut batches = requests.into_iter().peekable();
+
+        if batches.peek().is_none() {
+            return self
+                .catch_up_to_quorum_async(MaybeVec::from_many(Vec::new()))
+                .await;
+        }
+
+        while batches.peek().is_some() {
+            let chunk_batches: Vec<_> = batches.by_ref().take(chunk).collect();
+
+            self.catch_up_to_quorum_async(MaybeVec::from_many(chunk_batches))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Async version of [`ExecutorHandle::queue_update()`].
+    pub async fn queue_update_async(&self, batch: UpdateBatch<RQ>) -> ExecutorResult<()> {
+        self.shared.ensure_running()?;
+        check_batch_len(self.max_batch_len, &batch)?;
+
+        let (batch, now) = stamp_batch(batch);
+
+        let seq = batch.sequence_number();
+
+        self.shared.check_queued_seqs(&[seq]);
+
+        self.send_request(
+            ExecutionRequest::Update((batch, now)),
+            "Failed to place update order into executor channel",
+        )
+        .await?;
+
+        self.shared.record_queued_seq(seq);
+
+        Ok(())
+    }
+
+    /// Async version of [`ExecutorHandle::queue_update_many()`].
+    pub async fn queue_update_many_async(
+        &self,
+        batches: Vec<UpdateBatch<RQ>>,
+    ) -> ExecutorResult<()> {
+        self.shared.ensure_running()?;
+
+        for batch in &batches {
+            check_batch_len(self.max_batch_len, batch)?;
+        }
+
+        let now = Instant::now();
+        let seqs: Vec<_> = batches.iter().map(UpdateBatch::sequence_number).collect();
+
+        self.shared.check_queued_seqs(&seqs);
+
+        let batches = batches
+            .into_iter()
+            .map(|mut batch| {
+                batch.stamp_proposed_at(now);
+
+                batch
+            })
+            .collect();
+
+        self.send_request(
+            ExecutionRequest::UpdateMany((MaybeVec::from_many(batches), now)),
+            "Failed to place update many order into executor channel",
+        )
+        .await?;
+
+        for seq in seqs {
+            self.shared.record_queued_seq(seq);
+        }
+
+        Ok(())
+    }
+
+    /// Async version of [`ExecutorHandle::queue_update_no_reply()`].
+    pub async fn queue_update_no_reply_async(&self, batch: UpdateBatch<RQ>) -> ExecutorResult<()> {
+        self.shared.ensure_running()?;
+        check_batch_len(self.max_batch_len, &batch)?;
+
+        let (batch, now) = stamp_batch(batch);
+
+        let seq = batch.sequence_number();
+
+        self.shared.check_queued_seqs(&[seq]);
+
+        self.send_request(
+            ExecutionRequest::UpdateNoReply((batch, now)),
+            "Failed to place update with no reply order into executor channel",
+        )
+        .await?;
+
+        self.shared.record_queued_seq(seq);
+
+        Ok(())
+    }
+
+    /// Async version of [`ExecutorHandle::queue_update_unordered()`].
+    pub async fn queue_update_unordered_async(
+        &self,
+        requests: UnorderedBatch<RQ>,
+    ) -> ExecutorResult<()> {
+        self.shared.ensure_running()?;
+
+        self.send_request(
+            ExecutionRequest::ExecuteUnordered(requests),
+            "Failed to place unordered update order into executor channel",
+        )
+        .await
+    }
+
+    /// Async version of [`ExecutorHandle::queue_read()`].
+    pub async fn queue_read_async(&self, from: NodeId) -> ExecutorResult<()> {
+        self.shared.ensure_running()?;
+
+        self.send_request(
+            ExecutionRequest::read(from),
+            "Failed to place read order into executor channel",
+        )
+        .await
+    }
+
+    /// Async version of [`ExecutorHandle::queue_read_query()`].
+    pub async fn queue_read_query_async(&self, from: NodeId, query: RQ) -> ExecutorResult<()> {
+        self.shared.ensure_running()?;
+
+        self.send_request(
+            ExecutionRequest::read_query(from, query),
+            "Failed to place read order into executor channel",
+        )
+        .await
+    }
+
+    /// Async version of [`ExecutorHandle::request_checkpoint()`].
+    pub async fn request_checkpoint_async(&self) -> ExecutorResult<()> {
+        self.shared.ensure_running()?;
+
+        self.send_request(
+            ExecutionRequest::Checkpoint,
+            "Failed to place checkpoint order into executor channel",
+        )
+        .await
+    }
+
+    /// Async version of [`ExecutorHandle::barrier()`].
+    ///
+    /// Only queueing the barrier is awaited, [`BarrierWaiter::wait()`] still blocks.
+    pub async fn barrier_async(&self) -> ExecutorResult<BarrierWaiter> {
+        self.shared.ensure_running()?;
+
+        let (tx, rx) = new_oneshot_channel();
+
+        self.send_request(
+            ExecutionRequest::Barrier(tx),
+            "Failed to place barrier order into executor channel",
+        )
+        .await?;
+
+        Ok(BarrierWaiter { rx })
+    }
+
+    /// Async version of [`ExecutorHandle::queue_update_and_get_appstate()`].
+    pub async fn queue_update_and_get_appstate_async(
+        &self,
+        batch: UpdateBatch<RQ>,
+    ) -> ExecutorResult<()> {
+        self.shared.ensure_running()?;
+        check_batch_len(self.max_batch_len, &batch)?;
+
+        let (batch, now) = stamp_batch(batch);
+
+        let seq = batch.sequence_number();
+
+        self.shared.check_queued_seqs(&[seq]);
+
+        self.send_request(
+            ExecutionRequest::UpdateAndGetAppstate((batch, now)),
+            "Failed to place update and get appstate order into executor channel",
+        )
+        .await?;
+
+        self.shared.record_queued_seq(seq);
+
+        Ok(())
+    }
+
+    /// Async version of [`ExecutorHandle::shutdown()`].
+    pub async fn shutdown_async(&self) -> ExecutorResult<()> {
+        self.shared.begin_shutdown()?;
+
+        let result = self
+            .send_request(
+                ExecutionRequest::Shutdown(None),
+                "Failed to place shutdown order into executor channel",
+            )
+            .await;
+
+        if result.is_err() {
+            self.shared.abort_shutdown();
+        }
+
+        result
+    }
+}
+
+impl<RQ, RP> Clone for ExecutorHandleAsync<RQ, RP> {
+    fn clone(&self) -> Self {
+        let e_tx = self.e_tx.clone();
+        let shared = self.shared.clone();
+        let pending = self.pending.clone();
+        let max_batch_len = self.max_batch_len;
+
+        Self {
+            e_tx,
+            shared,
+            pending,
+            max_batch_len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    use atlas_common::channel::sync::ChannelSyncRx;
+    use atlas_common::channel::{new_bounded_mixed, new_bounded_sync};
+
+    use super::*;
+
+    type TestRx = ChannelSyncRx<ExecutionRequest<u64>>;
+
+    fn handle(capacity: usize) -> (ExecutorHandle<u64>, TestRx) {
+        let (tx, rx) = new_bounded_sync(capacity, Some("Test executor channel"));
+
+        (ExecutorHandle::new(tx), rx)
+    }
+
+    fn batch(seq: u32, len: u64) -> UpdateBatch<u64> {
+        let mut batch = UpdateBatch::new(SeqNo::from(seq));
+
+        for op in 0..len {
+            batch.add(NodeId(0), SeqNo::ZERO, SeqNo::from(op as u32), op);
+        }
+
+        batch
+    }
+
+    /// Drives a future to completion on the current thread
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+
+            std::thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn async_handle_rejects_after_shutdown() {
+        let (handle, _rx) = handle(8);
+        let (async_tx, _async_rx) = new_bounded_mixed(8, Some("Test async executor channel"));
+        let async_handle = handle.to_async(async_tx);
+
+        handle.shutdown().unwrap();
+
+        assert!(matches!(
+            block_on(async_handle.queue_update_async(batch(0, 1))),
+            Err(ExecutorError::ShutdownInProgress)
+        ));
+        assert!(matches!(
+            block_on(async_handle.poll_state_channel_async()),
+            Err(ExecutorError::ShutdownInProgress)
+        ));
+    }
+
+    #[test]
+    fn async_handle_shares_counters_and_limits() {
+        let (handle, _rx) = handle(8);
+        let handle = handle.with_max_batch_len(2);
+        let (async_tx, async_rx) = new_bounded_mixed(8, Some("Test async executor channel"));
+        let async_handle = handle.to_async(async_tx);
+
+        block_on(async_handle.queue_update_async(batch(0, 2))).unwrap();
+
+        assert_eq!(handle.pending_kinds().update, 1);
+        assert_eq!(handle.last_queued_seq(), Some(SeqNo::from(0)));
+        assert!(async_rx.recv().is_ok());
+
+        assert!(matches!(
+            block_on(async_handle.queue_update_async(batch(1, 3))),
+            Err(ExecutorError::BatchTooLarge { len: 3, max: 2 })
+        ));
+    }
+
+    #[test]
+    fn only_ordered_batches_carry_a_priority() {
+        let request: ExecutionRequest<u64> = ExecutionRequest::Update((
+            batch(0, 1).with_priority(BatchPriority::Low),
+            Instant::now(),
+        ));
+
+        assert_eq!(request.priority(), Some(BatchPriority::Low));
+
+        let request: ExecutionRequest<u64> =
+            ExecutionRequest::Update((batch(0, 1), Instant::now()));
+
+        assert_eq!(request.priority(), Some(BatchPriority::Normal));
+        assert_eq!(ExecutionRequest::<u64>::PollStateChannel.priority(), None);
+    }
+
+    #[test]
+    fn try_queue_update_hands_the_batch_back_when_full() {
+        let (handle, rx) = handle(1);
+
+        assert!(handle.try_queue_update(batch(0, 1)).is_ok());
+
+        let returned = handle
+            .try_queue_update(batch(1, 3))
+            .expect_err("The channel is full");
+
+        assert_eq!(returned.sequence_number(), SeqNo::from(1));
+        assert_eq!(returned.len(), 3);
+
+        rx.recv().unwrap();
+
+        assert!(handle.try_queue_update(returned).is_ok());
+    }
+
+    #[test]
+    fn pending_len_tracks_queued_requests() {
+        let (handle, rx) = handle(4);
+
+        assert_eq!(handle.capacity(), Some(4));
+        assert_eq!(handle.pending_len(), 0);
+
+        handle.poll_state_channel().unwrap();
+        handle.clone().request_checkpoint().unwrap();
+
+        assert_eq!(handle.pending_len(), 2);
+
+        rx.recv().unwrap();
+
+        assert_eq!(handle.pending_len(), 1);
+    }
+
+    #[test]
+    fn queue_read_enqueues_a_read() {
+        let (handle, rx) = handle(4);
+
+        handle.queue_read(NodeId(7)).unwrap();
+
+        assert!(matches!(
+            rx.recv().unwrap(),
+            ExecutionRequest::ReadQuery {
+                from: NodeId(7),
+                query: None
+            }
+        ));
+        assert_eq!(handle.pending_kinds().read, 1);
+    }
+
+    #[test]
+    fn pending_kinds_count_each_queued_kind() {
+        let (handle, rx) = handle(8);
+        let counters = handle.pending_counters();
+
+        handle.queue_update(batch(0, 1)).unwrap();
+        handle.queue_update(batch(1, 1)).unwrap();
+        handle
+            .queue_update_unordered(UnorderedBatch::new())
+            .unwrap();
+        handle.queue_read(NodeId(1)).unwrap();
+        handle
+            .catch_up_to_quorum(MaybeVec::from_one(batch(2, 1)))
+            .unwrap();
+
+        let counts = handle.pending_kinds();
+
+        assert_eq!(counts.update, 2);
+        assert_eq!(counts.execute_unordered, 1);
+        assert_eq!(counts.read, 1);
+        assert_eq!(counts.catch_up, 1);
+        assert_eq!(counts.barrier, 0);
+
+        counters.dequeued(&rx.recv().unwrap());
+
+        assert_eq!(handle.pending_kinds().update, 1);
+    }
+
+    #[test]
+    fn queue_update_no_reply_enqueues_the_batch() {
+        let (handle, rx) = handle(4);
+
+        handle.queue_update_no_reply(batch(0, 2)).unwrap();
+
+        assert!(matches!(
+            rx.recv().unwrap(),
+            ExecutionRequest::UpdateNoReply((batch, _)) if batch.len() == 2
+        ));
+    }
+
+    #[test]
+    fn request_checkpoint_enqueues_a_checkpoint() {
+        let (handle, rx) = handle(4);
+
+        handle.request_checkpoint().unwrap();
+
+        assert_eq!(rx.recv().unwrap().kind(), ExecutionRequestKind::Checkpoint);
+    }
+
+    /// The sequence numbers of the batches of every catch up request queued in `rx`
+    fn queued_catch_ups(rx: &TestRx) -> Vec<Vec<SeqNo>> {
+        let mut catch_ups = Vec::new();
+
+        while let Ok(request) = rx.try_recv() {
+            match request {
+                ExecutionRequest::CatchUp(batches) => catch_ups.push(
+                    batches
+                        .into_iter()
+                        .map(|batch| batch.sequence_number())
+                        .collect(),
+                ),
+                _ => panic!("Expected only catch up requests"),
+            }
+        }
+
+        catch_ups
+    }
+
+    #[test]
+    fn catch_up_chunked_splits_the_batches_in_order() {
+        let (handle, rx) = handle(8);
+
+        let batches = (0..7).map(|seq| batch(seq, 1)).collect();
+
+        handle
+            .catch_up_chunked(MaybeVec::from_many(batches), 3)
+            .unwrap();
+
+        let seqs = |range: std::ops::Range<u32>| range.map(SeqNo::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            queued_catch_ups(&rx),
+            vec![seqs(0..3), seqs(3..6), seqs(6..7)]
+        );
+    }
+
+    #[test]
+    fn catch_up_chunked_sends_empty_input_once() {
+        let (handle, rx) = handle(8);
+
+        handle
+            .catch_up_chunked(MaybeVec::from_many(Vec::new()), 3)
+            .unwrap();
+
+        assert_eq!(queued_catch_ups(&rx), vec![Vec::<SeqNo>::new()]);
+    }
+
+    #[test]
+    fn catch_up_chunked_rejects_empty_chunks() {
+        let (handle, rx) = handle(8);
+
+        let result = handle.catch_up_chunked(MaybeVec::from_one(batch(0, 1)), 0);
+
+        assert!(matches!(result, Err(ExecutorError::InvalidChunkSize)));
+        assert!(queued_catch_ups(&rx).is_empty());
+    }
+
+    #[test]
+    fn queue_update_blocking_waits_for_the_replies() {
+        let (tx, rx) = new_bounded_sync(4, Some("Test executor channel"));
+        let handle: ExecutorHandle<u64, u64> = ExecutorHandle::new(tx);
+
+        // Stub executor, which echoes every operation back as its reply
+        let executor = std::thread::spawn(move || match rx.recv().unwrap() {
+            ExecutionRequest::UpdateWithReplyChannel((batch, _, reply_tx)) => {
+                let mut replies = BatchReplies::with_capacity(batch.len());
+
+                for update in batch.into_inner() {
+                    let (from, session, op_id, operation) = update.into_inner();
+
+                    replies.add(from, session, op_id, operation);
+                }
+
+                reply_tx.send(replies).unwrap();
+            }
+            _ => panic!("Expected an update with a reply channel"),
+        });
+
+        let replies = handle.queue_update_blocking(batch(1, 3)).unwrap();
+
+        executor.join().unwrap();
+
+        assert_eq!(
+            replies
+                .into_inner()
+                .into_iter()
+                .map(|reply| reply.into_inner().3)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn every_request_maps_to_its_kind() {
+        let now = Instant::now();
+
+        let requests: Vec<(ExecutionRequest<u64, u64>, ExecutionRequestKind)> = vec![
+            (
+                ExecutionRequest::PollStateChannel,
+                ExecutionRequestKind::PollStateChannel,
+            ),
+            (
+                ExecutionRequest::CatchUp(MaybeVec::from_one(batch(1, 1))),
+                ExecutionRequestKind::CatchUp,
+            ),
+            (
+                ExecutionRequest::Update((batch(1, 1), now)),
+                ExecutionRequestKind::Update,
+            ),
+            (
+                ExecutionRequest::UpdateMany((MaybeVec::from_one(batch(1, 1)), now)),
+                ExecutionRequestKind::UpdateMany,
+            ),
+            (
+                ExecutionRequest::UpdateAndGetAppstate((batch(1, 1), now)),
+                ExecutionRequestKind::UpdateAndGetAppstate,
+            ),
+            (
+                ExecutionRequest::UpdateNoReply((batch(1, 1), now)),
+                ExecutionRequestKind::UpdateNoReply,
+            ),
+            (
+                ExecutionRequest::UpdateWithReplyChannel((
+                    batch(1, 1),
+                    now,
+                    new_oneshot_channel().0,
+                )),
+                ExecutionRequestKind::UpdateWithReplyChannel,
+            ),
+            (
+                ExecutionRequest::ExecuteUnordered(UnorderedBatch::new()),
+                ExecutionRequestKind::ExecuteUnordered,
+            ),
+            (
+                ExecutionRequest::read(NodeId(0)),
+                ExecutionRequestKind::Read,
+            ),
+            (
+                ExecutionRequest::Checkpoint,
+                ExecutionRequestKind::Checkpoint,
+            ),
+            (
+                ExecutionRequest::Barrier(new_oneshot_channel().0),
+                ExecutionRequestKind::Barrier,
+            ),
+            (
+                ExecutionRequest::Shutdown(None),
+                ExecutionRequestKind::Shutdown,
+            ),
+        ];
+
+        for (request, kind) in requests {
+            assert_eq!(request.kind(), kind);
+        }
+    }
+
+    #[test]
+    fn stats_are_recorded_by_the_execution_loop() {
+        let (tx, rx) = new_bounded_sync(8, Some("Test executor channel"));
+        let stats = Arc::new(ExecutorStats::default());
+
+        let handle: ExecutorHandle<u64> = ExecutorHandle::with_stats(tx, stats.clone());
+
+        // Mock execution loop, which only records the work it is handed
+        let executor = std::thread::spawn(move || loop {
+            match rx.recv().unwrap() {
+                ExecutionRequest::Update((batch, _)) => stats.record_ordered(batch.len() as u64),
+                ExecutionRequest::ExecuteUnordered(batch) => {
+                    stats.record_unordered(batch.len() as u64)
+                }
+                ExecutionRequest::Shutdown(_) => break,
+                _ => {}
+            }
+        });
+
+        let mut unordered = UnorderedBatch::new();
+        unordered.add(NodeId(0), SeqNo::ZERO, SeqNo::ZERO, 0);
+
+        handle.queue_update(batch(1, 3)).unwrap();
+        handle.queue_update(batch(2, 2)).unwrap();
+        handle.queue_update_unordered(unordered).unwrap();
+        handle.shutdown().unwrap();
+
+        executor.join().unwrap();
+
+        assert_eq!(handle.ordered_executed(), 5);
+        assert_eq!(handle.unordered_executed(), 1);
+    }
+
+    #[test]
+    fn handles_without_stats_report_no_work() {
+        let (handle, _rx) = handle(4);
+
+        handle.queue_update(batch(1, 3)).unwrap();
+
+        assert_eq!(handle.ordered_executed(), 0);
+        assert_eq!(handle.unordered_executed(), 0);
+    }
+
+    #[test]
+    fn queue_update_many_sends_the_batches_as_one_request() {
+        let (handle, rx) = handle(8);
+
+        handle.queue_update(batch(1, 1)).unwrap();
+        handle
+            .queue_update_many(vec![batch(2, 1), batch(3, 2), batch(4, 1)])
+            .unwrap();
+        handle.queue_update(batch(5, 1)).unwrap();
+
+        assert!(matches!(rx.try_recv(), Ok(ExecutionRequest::Update(_))));
+
+        match rx.try_recv() {
+            Ok(ExecutionRequest::UpdateMany((batches, _))) => assert_eq!(
+                batches
+                    .into_iter()
+                    .map(|batch| batch.sequence_number())
+                    .collect::<Vec<_>>(),
+                vec![SeqNo::from(2), SeqNo::from(3), SeqNo::from(4)]
+            ),
+            _ => panic!("Expected the batches to be queued together"),
+        }
+
+        assert!(matches!(rx.try_recv(), Ok(ExecutionRequest::Update(_))));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(handle.last_queued_seq(), Some(SeqNo::from(5)));
+    }
+
+    #[test]
+    fn chunks_sharing_a_sequence_number_can_be_queued_together() {
+        let (handle, _rx) = handle(8);
+
+        handle.queue_update(batch(2, 1)).unwrap();
+        handle
+            .queue_update_many(batch(3, 4).split_into_chunks(2))
+            .unwrap();
+
+        assert_eq!(handle.last_queued_seq(), Some(SeqNo::from(3)));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "breaking sequence number order")]
+    fn repeated_sequence_numbers_trigger_the_debug_assertion() {
+        let (handle, _rx) = handle(8);
+
+        handle.queue_update(batch(5, 1)).unwrap();
+        let _ = handle.queue_update(batch(5, 1));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn regressing_batches_are_not_sent() {
+        let (handle, rx) = handle(8);
+
+        handle.queue_update(batch(5, 1)).unwrap();
+
+        let regressing = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            handle.queue_update_many(vec![batch(6, 1), batch(4, 1)])
+        }));
+
+        assert!(regressing.is_err());
+        assert!(matches!(rx.try_recv(), Ok(ExecutionRequest::Update(_))));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(handle.last_queued_seq(), Some(SeqNo::from(5)));
+    }
+
+    #[test]
+    fn failed_sends_do_not_record_the_sequence_number() {
+        let (handle, rx) = handle(1);
+        drop(rx);
+
+        assert!(handle.queue_update(batch(3, 1)).is_err());
+        assert_eq!(handle.last_queued_seq(), None);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "breaking sequence number order")]
+    fn regressing_sequence_numbers_trigger_the_debug_assertion() {
+        let (handle, _rx) = handle(8);
+
+        handle.queue_update(batch(5, 1)).unwrap();
+        let _ = handle.queue_update(batch(4, 1));
+    }
+
+    #[test]
+    fn queue_read_query_enqueues_a_parameterized_read() {
+        let (handle, rx) = handle(4);
+
+        handle.queue_read_query(NodeId(7), 42).unwrap();
+
+        assert!(matches!(
+            rx.recv().unwrap(),
+            ExecutionRequest::ReadQuery {
+                from: NodeId(7),
+                query: Some(42)
+            }
+        ));
+        assert_eq!(handle.pending_kinds().read, 1);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn bare_reads_are_still_reads() {
+        let request: ExecutionRequest<u64> = ExecutionRequest::Read(NodeId(7));
+
+        assert_eq!(request.kind(), ExecutionRequestKind::Read);
+        assert_eq!(
+            ExecutionRequest::<u64>::read(NodeId(7)).kind(),
+            ExecutionRequestKind::Read
+        );
+    }
+
+    #[test]
+    fn queue_update_with_retry_succeeds_once_the_channel_frees_up() {
+        let (handle, rx) = handle(1);
+        let backoff = Duration::from_millis(200);
+
+        handle.queue_update(batch(1, 1)).unwrap();
+
+        // The channel is full on the first attempt, and frees up before the second one
+        let executor = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            rx.recv().unwrap();
+
+            rx
+        });
+
+        let start = Instant::now();
+
+        handle
+            .queue_update_with_retry(batch(2, 1), 3, backoff)
+            .unwrap();
+
+        assert!(start.elapsed() >= backoff);
+
+        let rx = executor.join().unwrap();
+
+        assert!(matches!(rx.try_recv(), Ok(ExecutionRequest::Update(_))));
+        assert_eq!(handle.last_queued_seq(), Some(SeqNo::from(2)));
+    }
+
+    #[test]
+    fn queue_update_with_retry_gives_up_after_every_attempt() {
+        let (handle, _rx) = handle(1);
+
+        handle.queue_update(batch(1, 1)).unwrap();
+
+        let rejected = handle
+            .queue_update_with_retry(batch(2, 1), 2, Duration::from_millis(1))
+            .unwrap_err();
+
+        let (batch, error) = *rejected;
+
+        assert!(matches!(error, ExecutorError::ChannelFull { attempts: 2 }));
+        assert_eq!(batch.sequence_number(), SeqNo::from(2));
+        assert_eq!(handle.last_queued_seq(), Some(SeqNo::from(1)));
+    }
+
+    #[test]
+    fn queue_update_with_retry_attempts_at_least_once() {
+        let (handle, rx) = handle(1);
+
+        handle
+            .queue_update_with_retry(batch(1, 1), 0, Duration::ZERO)
+            .unwrap();
+
+        assert!(matches!(rx.try_recv(), Ok(ExecutionRequest::Update(_))));
+
+        handle.queue_update(batch(2, 1)).unwrap();
+
+        let rejected = handle
+            .queue_update_with_retry(batch(3, 1), 0, Duration::ZERO)
+            .unwrap_err();
+
+        assert!(matches!(
+            rejected.1,
+            ExecutorError::ChannelFull { attempts: 1 }
+        ));
+    }
+
+    #[test]
+    fn queue_update_with_retry_hands_the_batch_back_when_closed() {
+        let (handle, rx) = handle(1);
+        drop(rx);
+
+        let rejected = handle
+            .queue_update_with_retry(batch(1, 3), 5, Duration::from_secs(60))
+            .unwrap_err();
+
+        assert!(matches!(rejected.1, ExecutorError::ChannelClosed { .. }));
+        assert_eq!(rejected.0.len(), 3);
+    }
+
+    #[test]
+    fn handles_can_be_used_as_trait_objects() {
+        let (handle, rx) = handle(4);
+
+        let executor: Box<dyn Executor<u64>> = Box::new(handle);
+
+        executor.queue_update(batch(1, 2)).unwrap();
+        executor.request_checkpoint().unwrap();
+
+        match rx.try_recv() {
+            Ok(ExecutionRequest::Update((batch, _))) => {
+                assert_eq!(batch.sequence_number(), SeqNo::ONE);
+                assert_eq!(batch.len(), 2);
+            }
+            _ => panic!("Expected the update to be queued"),
+        }
+
+        assert!(matches!(rx.try_recv(), Ok(ExecutionRequest::Checkpoint)));
+    }
+
+    #[test]
+    fn barrier_completes_after_prior_updates() {
+        let (handle, rx) = handle(8);
+        let executed = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let executor = {
+            let executed = executed.clone();
+
+            // Mock execution loop, which takes a while to execute each update
+            std::thread::spawn(move || loop {
+                match rx.recv().unwrap() {
+                    ExecutionRequest::Update((batch, _)) => {
+                        std::thread::sleep(Duration::from_millis(5));
+                        executed.lock().unwrap().push(batch.sequence_number());
+                    }
+                    ExecutionRequest::Barrier(tx) => tx.send(()).unwrap(),
+                    ExecutionRequest::Shutdown(_) => break,
+                    _ => {}
+                }
+            })
+        };
+
+        for seq in 1..=3 {
+            handle.queue_update(batch(seq, 1)).unwrap();
+        }
+
+        handle.barrier().unwrap().wait().unwrap();
+
+        assert_eq!(
+            *executed.lock().unwrap(),
+            vec![SeqNo::from(1), SeqNo::from(2), SeqNo::from(3)]
+        );
+
+        handle.shutdown().unwrap();
+        executor.join().unwrap();
+    }
+
+    #[test]
+    fn dropping_every_clone_is_detected() {
+        let (handle, _rx) = handle(4);
+        let liveness = handle.liveness();
+
+        let clones = vec![handle.clone(), handle.clone()];
+
+        assert!(!handle.is_last());
+
+        drop(clones);
+
+        assert!(handle.is_last());
+        assert!(!liveness.all_dropped());
+
+        drop(handle);
+
+        assert!(liveness.all_dropped());
+    }
+
+    #[test]
+    fn closed_channels_surface_as_channel_closed() {
+        let (handle, rx) = handle(4);
+        drop(rx);
+
+        let err = handle.queue_update(batch(1, 1)).unwrap_err();
+
+        assert!(matches!(err, ExecutorError::ChannelClosed { .. }));
+
+        // The variant survives the conversion into the crate wide error type
+        let err: anyhow::Error = err.into();
+
+        assert!(matches!(
+            err.downcast_ref::<ExecutorError>(),
+            Some(ExecutorError::ChannelClosed { .. })
+        ));
+    }
+
+    #[test]
+    fn full_channels_surface_as_channel_full() {
+        let (handle, _rx) = handle(1);
+
+        handle.queue_update(batch(1, 1)).unwrap();
+
+        assert!(matches!(
+            handle
+                .try_send_update(batch(2, 1))
+                .map_err(|rejected| *rejected),
+            Err((_, ExecutorError::ChannelFull { attempts: 1 }))
+        ));
+    }
+
+    #[test]
+    fn oversized_batches_surface_as_batch_too_large() {
+        let (handle, rx) = handle(4);
+        let handle = handle.with_max_batch_len(2);
+
+        assert!(matches!(
+            handle.queue_update(batch(1, 3)),
+            Err(ExecutorError::BatchTooLarge { len: 3, max: 2 })
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn trace_context_survives_being_handed_back_and_queued() {
+        let (handle, rx) = handle(1);
+
+        handle.queue_update(batch(1, 1)).unwrap();
+
+        let traced = batch(2, 1).with_trace_context(vec![1, 2, 3]);
+
+        let returned = handle.try_queue_update(traced).unwrap_err();
+
+        assert_eq!(returned.trace_context(), Some(&[1u8, 2, 3][..]));
+
+        rx.recv().unwrap();
+        handle.try_queue_update(returned).unwrap();
+
+        match rx.recv().unwrap() {
+            ExecutionRequest::Update((batch, _)) => {
+                assert_eq!(batch.trace_context(), Some(&[1u8, 2, 3][..]))
+            }
+            _ => panic!("Expected the traced update to be queued"),
+        }
+    }
+
+    #[test]
+    fn async_handle_mirrors_the_queueing_methods() {
+        let (handle, _rx) = handle(8);
+        let (async_tx, async_rx) = new_bounded_mixed(16, Some("Test async executor channel"));
+        let async_handle = handle.to_async(async_tx);
+
+        block_on(async_handle.queue_update_no_reply_async(batch(0, 1))).unwrap();
+        block_on(async_handle.queue_update_many_async(vec![batch(1, 1), batch(2, 1)])).unwrap();
+        block_on(async_handle.queue_read_async(NodeId(1))).unwrap();
+        block_on(async_handle.queue_read_query_async(NodeId(1), 5)).unwrap();
+        block_on(async_handle.request_checkpoint_async()).unwrap();
+        let _waiter = block_on(async_handle.barrier_async()).unwrap();
+        block_on(async_handle.catch_up_chunked_async(
+            MaybeVec::from_many(vec![batch(3, 1), batch(4, 1), batch(5, 1)]),
+            2,
+        ))
+        .unwrap();
+
+        let kinds: Vec<_> = (0..8).map(|_| async_rx.recv().unwrap().kind()).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                ExecutionRequestKind::UpdateNoReply,
+                ExecutionRequestKind::UpdateMany,
+                ExecutionRequestKind::Read,
+                ExecutionRequestKind::Read,
+                ExecutionRequestKind::Checkpoint,
+                ExecutionRequestKind::Barrier,
+                ExecutionRequestKind::CatchUp,
+                ExecutionRequestKind::CatchUp,
+            ]
+        );
+
+        assert_eq!(handle.last_queued_seq(), Some(SeqNo::from(2)));
+        assert!(matches!(
+            block_on(async_handle.catch_up_chunked_async(MaybeVec::from_many(vec![]), 0)),
+            Err(ExecutorError::InvalidChunkSize)
+        ));
+    }
+
+    #[test]
+    fn async_shutdown_stops_every_handle() {
+        let (handle, _rx) = handle(8);
+        let (async_tx, async_rx) = new_bounded_mixed(8, Some("Test async executor channel"));
+        let async_handle = handle.to_async(async_tx);
+
+        block_on(async_handle.shutdown_async()).unwrap();
+
+        assert!(matches!(
+            async_rx.recv().unwrap(),
+            ExecutionRequest::Shutdown(None)
+        ));
+        assert!(handle.is_shutting_down());
+        assert!(matches!(
+            handle.queue_update(batch(0, 1)),
+            Err(ExecutorError::ShutdownInProgress)
+        ));
+        assert!(matches!(
+            block_on(async_handle.shutdown_async()),
+            Err(ExecutorError::ShutdownInProgress)
+        ));
+    }
+
+    #[test]
+    fn trait_objects_reach_every_queueing_method() {
+        let (handle, rx) = handle(16);
+
+        let executor: Box<dyn Executor<u64>> = Box::new(handle);
+
+        executor
+            .catch_up_chunked(MaybeVec::from_many(vec![batch(0, 1)]), 1)
+            .unwrap();
+        executor
+            .queue_update_many(vec![batch(1, 1), batch(2, 1)])
+            .unwrap();
+        executor.try_queue_update(batch(3, 1)).unwrap();
+        executor
+            .queue_update_with_retry(batch(4, 1), 1, Duration::ZERO)
+            .unwrap();
+        executor.queue_read_query(NodeId(2), 7).unwrap();
+        let _waiter = executor.barrier().unwrap();
+
+        let kinds: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|request| request.kind())
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                ExecutionRequestKind::CatchUp,
+                ExecutionRequestKind::UpdateMany,
+                ExecutionRequestKind::Update,
+                ExecutionRequestKind::Update,
+                ExecutionRequestKind::Read,
+                ExecutionRequestKind::Barrier,
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_shutdowns_do_not_reject_further_requests() {
+        let (handle, rx) = handle(1);
+
+        // The shutdown order cannot reach the executor
+        drop(rx);
+
+        assert!(matches!(
+            handle.shutdown(),
+            Err(ExecutorError::ChannelClosed { .. })
+        ));
+
+        assert!(!handle.is_shutting_down());
+        assert!(matches!(
+            handle.queue_update(batch(1, 1)),
+            Err(ExecutorError::ChannelClosed { .. })
+        ));
+        assert!(matches!(
+            handle.shutdown(),
+            Err(ExecutorError::ChannelClosed { .. })
+        ));
     }
 }
@@ -1,8 +1,13 @@
-use atlas_common::crypto::hash::Digest;
+use atlas_common::crypto::hash::{Context, Digest};
 use atlas_common::error::*;
 use atlas_common::maybe_vec::MaybeVec;
 use atlas_common::ordering::{Orderable, SeqNo};
 use atlas_common::serialization_helper::SerMsg;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+use crate::app::BatchReplies;
+use crate::state::progress_fraction;
 
 /// Messages to be sent from the state transfer module to the
 /// executor module
@@ -14,6 +19,20 @@ where
     StateDescriptor(S::StateDescriptor),
     /// We have received a part of the state
     StatePart(MaybeVec<S::StatePart>),
+    /// The wall clock time at which the checkpoint being installed was created,
+    /// so the executor can measure how stale it is. Purely informational.
+    CheckpointCreatedAt(SystemTime),
+    /// Report of how far the installation of the state has progressed
+    Progress {
+        received_parts: usize,
+        total_parts: usize,
+    },
+    /// The installation in progress has been abandoned (e.g. a newer checkpoint was received).
+    ///
+    /// Upon receiving this, the executor must discard any parts accepted since the
+    /// last [`InstallStateMessage::StateDescriptor`] and revert to the descriptor it
+    /// had before the installation started.
+    Cancel { reason: String },
     /// We can go back to polling the regular channel for new messages, as we are done installing state
     Done,
 }
@@ -25,7 +44,13 @@ where
     S: DivisibleState,
 {
     StateDescriptor(S::StateDescriptor),
+    /// A chunk of the parts of the checkpoint, with more chunks to follow
     StatePart(MaybeVec<S::StatePart>),
+    /// The last chunk of the parts of the checkpoint, no more parts follow it
+    StatePartFinal(MaybeVec<S::StatePart>),
+    /// Nothing changed since the last checkpoint, so the previously known descriptor
+    /// is still current and no parts need to be transferred
+    NoChange,
     Done,
 }
 
@@ -36,9 +61,117 @@ where
     S: DivisibleState,
 {
     seq_no: SeqNo,
+    /// The position of this message within the checkpoint `seq_no`,
+    /// for checkpoints that are sent over multiple messages
+    part_sequence: Option<SeqNo>,
+    /// The wall clock time at which the checkpoint was created.
+    /// A [`SystemTime`] is used as this is carried across processes.
+    created_at: Option<SystemTime>,
+    state: AppState<S>,
+}
+
+/// Errors produced when handling a divisible state
+#[derive(Error, Debug)]
+pub enum DivisibleStateError {
+    #[error("State part at index {index} does not match its content description {expected:?}")]
+    PartDigestMismatch { index: usize, expected: Digest },
+    #[error("{0} is not supported by this state")]
+    NotSupported(&'static str),
+    #[error("Cannot migrate state from version {from} to version {current}")]
+    UnsupportedMigration { from: u32, current: u32 },
+    #[error("State part of {size} bytes exceeds the maximum part size of {max} bytes")]
+    PartTooLarge { size: usize, max: usize },
+}
+
+/// The result of executing a batch with [`crate::ExecutionRequest::UpdateAndGetAppstate`],
+/// pairing the replies of the batch with the checkpointed application state
+pub struct CheckpointExecutionResult<P, S>
+where
+    S: DivisibleState,
+{
+    replies: BatchReplies<P>,
     state: AppState<S>,
 }
 
+/// The compression algorithms that can be applied to state parts before they are transferred
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CompressionKind {
+    #[default]
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl CompressionKind {
+    /// Compresses `data` with this algorithm
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            CompressionKind::None => Ok(data.to_vec()),
+            #[cfg(feature = "compression_lz4")]
+            CompressionKind::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+            #[cfg(not(feature = "compression_lz4"))]
+            CompressionKind::Lz4 => {
+                Err(DivisibleStateError::NotSupported("Lz4 compression").into())
+            }
+            CompressionKind::Zstd => {
+                Err(DivisibleStateError::NotSupported("Zstd compression").into())
+            }
+        }
+    }
+
+    /// Decompresses `data`, which was compressed with this algorithm, failing with
+    /// [`DivisibleStateError::PartTooLarge`] if it decompresses to more than `max_size` bytes.
+    ///
+    /// The size is checked before decompressing, as `data` is received from other
+    /// (possibly faulty) replicas and must not be able to force large allocations.
+    pub fn decompress(&self, data: &[u8], max_size: usize) -> Result<Vec<u8>> {
+        match self {
+            CompressionKind::None => {
+                check_part_size(data.len(), max_size)?;
+
+                Ok(data.to_vec())
+            }
+            #[cfg(feature = "compression_lz4")]
+            CompressionKind::Lz4 => {
+                // The uncompressed size is prepended as a little endian u32
+                let size = data
+                    .get(..4)
+                    .map(|prefix| u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]))
+                    .ok_or_else(|| anyhow::anyhow!("Lz4 compressed part is missing its size"))?;
+
+                check_part_size(size as usize, max_size)?;
+
+                Ok(lz4_flex::decompress_size_prepended(data)?)
+            }
+            #[cfg(not(feature = "compression_lz4"))]
+            CompressionKind::Lz4 => {
+                Err(DivisibleStateError::NotSupported("Lz4 decompression").into())
+            }
+            CompressionKind::Zstd => {
+                Err(DivisibleStateError::NotSupported("Zstd decompression").into())
+            }
+        }
+    }
+}
+
+fn check_part_size(size: usize, max: usize) -> Result<()> {
+    if size > max {
+        return Err(DivisibleStateError::PartTooLarge { size, max }.into());
+    }
+
+    Ok(())
+}
+
+/// The differences between two state descriptors, as computed by [`DivisibleStateDescriptor::diff()`]
+pub struct StateDiff<S>
+where
+    S: DivisibleState,
+{
+    added: Vec<S::PartDescription>,
+    removed: Vec<S::PartDescription>,
+    modified: Vec<S::PartDescription>,
+}
+
 /// The trait that represents the ID of a part
 pub trait PartId: PartialEq + PartialOrd + Clone {
     fn content_description(&self) -> Digest;
@@ -53,11 +186,115 @@ pub trait DivisibleStateDescriptor<S: DivisibleState>:
 
     /// Compare two states
     fn compare_descriptors(&self, other: &Self) -> Vec<S::PartDescription>;
+
+    /// Compute the differences between this state and `other`, telling apart the parts
+    /// that have to be added, removed and modified to go from this state to `other`.
+    ///
+    /// By default, parts are matched by their position in `parts()`, so a part is
+    /// modified when both descriptors have a differing part at that position.
+    /// States whose parts are not positional should override this.
+    fn diff(&self, other: &Self) -> StateDiff<S> {
+        let ours = self.parts();
+        let theirs = other.parts();
+
+        let modified = ours
+            .iter()
+            .zip(theirs.iter())
+            .filter(|(our_part, their_part)| our_part != their_part)
+            .map(|(_, their_part)| their_part.clone())
+            .collect();
+
+        let added = theirs.iter().skip(ours.len()).cloned().collect();
+        let removed = ours.iter().skip(theirs.len()).cloned().collect();
+
+        StateDiff {
+            added,
+            removed,
+            modified,
+        }
+    }
+
+    /// The order in which the given parts should be fetched when installing this state.
+    ///
+    /// Lets applications front load parts that are needed to resume serving requests
+    /// (e.g. an index). By default, the given order is kept.
+    fn prioritize(&self, parts: &[S::PartDescription]) -> Vec<S::PartDescription> {
+        parts.to_vec()
+    }
+
+    /// An estimate of how much must be transferred to go from this state to `other`.
+    ///
+    /// By default this is the amount of differing parts, but implementations may override
+    /// it to sum the size of each differing part. Used to decide between an incremental
+    /// and a full transfer.
+    fn delta_size_hint(&self, other: &Self) -> usize {
+        self.compare_descriptors(other).len()
+    }
+
+    /// An estimate of the total size, in bytes, of the state described by this descriptor,
+    /// reported before a transfer starts.
+    ///
+    /// Unknown (`None`) by default. Implementations that know the size of their parts
+    /// should override it to sum them.
+    fn total_size_hint(&self) -> Option<usize> {
+        None
+    }
+
+    /// The version of the state format (see [`DivisibleState::state_version()`]) of the
+    /// checkpoint described by this descriptor.
+    ///
+    /// When installing a checkpoint whose descriptor reports a version other than the
+    /// `state_version()` of the local state, the install path calls
+    /// [`DivisibleState::migrate_from()`] with this version once every part is accepted.
+    /// Defaults to 0, matching the default `state_version()`.
+    fn state_version(&self) -> u32 {
+        0
+    }
 }
 
 /// A part of the state
 pub trait StatePart<S: DivisibleState> {
     fn descriptor(&self) -> S::PartDescription;
+
+    /// Recompute the digest of this part and check it matches the content description
+    /// of its descriptor.
+    ///
+    /// By default, this hashes the output of `serialize_part()`. Parts that cannot be
+    /// serialized never verify, so states that do not implement `serialize_part()` (or whose
+    /// content description is computed differently) must override this.
+    fn verify_digest(&self) -> bool {
+        let Ok(serialized) = self.serialize_part() else {
+            return false;
+        };
+
+        let mut ctx = Context::new();
+
+        ctx.update(&serialized);
+
+        ctx.finish() == self.descriptor().content_description()
+    }
+
+    /// Serialize this part, so it can be transferred as raw bytes.
+    /// By default, raw serialization of parts is not supported.
+    fn serialize_part(&self) -> Result<Vec<u8>> {
+        Err(DivisibleStateError::NotSupported("Raw part serialization").into())
+    }
+
+    /// Serialize and compress this part, to be transferred.
+    ///
+    /// A part does not know the [`DivisibleState::compression_algorithm()`] of its state, so
+    /// by default this delegates to the uncompressed `serialize_part()`, matching the default
+    /// `CompressionKind::None`. Senders of states that negotiate compression should use
+    /// [`DivisibleState::compress_part()`] instead, which reads the algorithm from the state.
+    fn compressed(&self) -> Result<Vec<u8>> {
+        self.serialize_part()
+    }
+
+    /// Serialize and compress this part with `kind`, to be transferred.
+    /// By default, this compresses the output of `serialize_part()`.
+    fn compressed_with(&self, kind: CompressionKind) -> Result<Vec<u8>> {
+        kind.compress(&self.serialize_part()?)
+    }
 }
 
 ///
@@ -73,14 +310,246 @@ pub trait DivisibleState: Sized + Send {
     /// Get the description of the state at this moment
     fn get_descriptor(&self) -> &Self::StateDescriptor;
 
+    /// A digest of the whole state, obtained by folding the content description of
+    /// each of its parts, in descriptor order.
+    ///
+    /// Replicas holding the same state produce the same hash, so a single digest can
+    /// be compared after installing a state, instead of every part.
+    fn state_hash(&self) -> Digest {
+        let mut ctx = Context::new();
+
+        for part in self.get_descriptor().parts() {
+            ctx.update(part.content_description().as_ref());
+        }
+
+        ctx.finish()
+    }
+
+    /// The version of the format of this state, to be bumped whenever it changes
+    /// in a way that requires checkpoints taken by older releases to be migrated.
+    fn state_version(&self) -> u32 {
+        0
+    }
+
+    /// Upgrade this state, freshly installed from a checkpoint with format `version`,
+    /// to the current `state_version()`.
+    ///
+    /// Called by the install path when the version of the incoming checkpoint, as reported by
+    /// [`DivisibleStateDescriptor::state_version()`], differs from ours. By default, no
+    /// migrations are supported, so any version other than the current one is an error.
+    fn migrate_from(&mut self, version: u32) -> Result<()> {
+        let current = self.state_version();
+
+        if version != current {
+            return Err(DivisibleStateError::UnsupportedMigration {
+                from: version,
+                current,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Reset this state to its empty (initial) form, dropping every part, so a full
+    /// install of a completely different state does not have to be compared against
+    /// stale parts.
+    ///
+    /// After this returns, `get_descriptor()` must describe an empty state.
+    /// By default, clearing is not supported.
+    fn clear(&mut self) -> Result<()> {
+        Err(DivisibleStateError::NotSupported("Clearing the state").into())
+    }
+
     /// Accept a number of parts into our current state
+    ///
+    /// An installation may be cancelled between any two calls to this method
+    /// (see [`InstallStateMessage::Cancel`]), so the state must remain consistent
+    /// enough to be reverted after each call.
     fn accept_parts(&mut self, parts: Vec<Self::StatePart>) -> Result<()>;
 
+    /// Much like `accept_parts()`, but first verifies every part against the content
+    /// description of its descriptor (see [`StatePart::verify_digest()`]).
+    ///
+    /// If any part fails verification, none of the parts are accepted and the
+    /// error identifies the first mismatching part.
+    fn accept_parts_verified(&mut self, parts: Vec<Self::StatePart>) -> Result<()> {
+        if let Some((index, part)) = parts
+            .iter()
+            .enumerate()
+            .find(|(_, part)| !part.verify_digest())
+        {
+            return Err(DivisibleStateError::PartDigestMismatch {
+                index,
+                expected: part.descriptor().content_description(),
+            }
+            .into());
+        }
+
+        self.accept_parts(parts)
+    }
+
+    /// How many parts the sender should batch into each [`AppState::StatePart`] message
+    /// when transferring this state, to avoid saturating the network during recovery.
+    ///
+    /// States with tiny or huge parts should override the default of 64.
+    fn transfer_chunk_size(&self) -> usize {
+        64
+    }
+
+    /// The compression algorithm applied to the parts of this state when they are transferred
+    fn compression_algorithm(&self) -> CompressionKind {
+        CompressionKind::None
+    }
+
+    /// Serialize and compress `part` with this state's `compression_algorithm()`, which is
+    /// what the receiver decompresses with in `accept_compressed_parts()`.
+    fn compress_part(&self, part: &Self::StatePart) -> Result<Vec<u8>> {
+        part.compressed_with(self.compression_algorithm())
+    }
+
+    /// The maximum size, in bytes, of a serialized part of this state.
+    ///
+    /// Received parts that exceed it are rejected before being decompressed.
+    /// States with larger parts should override the default of 64 MiB.
+    fn max_part_size(&self) -> usize {
+        64 * 1024 * 1024
+    }
+
+    /// Deserialize a part that was serialized with [`StatePart::serialize_part()`].
+    /// By default, raw serialization of parts is not supported.
+    fn deserialize_part(&self, _part: &[u8]) -> Result<Self::StatePart> {
+        Err(DivisibleStateError::NotSupported("Raw part deserialization").into())
+    }
+
+    /// Accept a number of compressed parts (see [`DivisibleState::compress_part()`]) into our current state.
+    ///
+    /// By default, each part is decompressed with our `compression_algorithm()` (limited to
+    /// `max_part_size()`), deserialized with `deserialize_part()` and then handed to `accept_parts()`.
+    fn accept_compressed_parts(&mut self, parts: Vec<Vec<u8>>) -> Result<()> {
+        let compression = self.compression_algorithm();
+        let max_part_size = self.max_part_size();
+
+        let parts = parts
+            .iter()
+            .map(|part| self.deserialize_part(&compression.decompress(part, max_part_size)?))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.accept_parts(parts)
+    }
+
     /// Prepare a checkpoint of the state
     fn prepare_checkpoint(&mut self) -> Result<&Self::StateDescriptor>;
 
+    /// Prepare a checkpoint of the state, also returning all of its parts.
+    ///
+    /// By default, this prepares the checkpoint with `prepare_checkpoint()` and then
+    /// fetches every part of the resulting descriptor with `get_parts()`. States that
+    /// can produce both in a single pass should override it.
+    fn prepare_checkpoint_with_parts(
+        &mut self,
+    ) -> Result<(&Self::StateDescriptor, Vec<Self::StatePart>)> {
+        self.prepare_checkpoint()?;
+
+        let descriptor = self.get_descriptor();
+        let parts = self.get_parts(descriptor.parts())?;
+
+        Ok((descriptor, parts))
+    }
+
+    /// Prepare a checkpoint of the state, also returning the parts that changed since
+    /// the checkpoint described by `previous`, to plan an incremental transfer.
+    ///
+    /// By default, this prepares the checkpoint with `prepare_checkpoint()` and then
+    /// compares `previous` against the new descriptor with `compare_descriptors()`.
+    fn prepare_checkpoint_delta(
+        &mut self,
+        previous: &Self::StateDescriptor,
+    ) -> Result<(&Self::StateDescriptor, Vec<Self::PartDescription>)> {
+        let descriptor = self.prepare_checkpoint()?;
+        let changed = previous.compare_descriptors(descriptor);
+
+        Ok((descriptor, changed))
+    }
+
     /// Get the parts corresponding to the provided part descriptions
     fn get_parts(&self, parts: &[Self::PartDescription]) -> Result<Vec<Self::StatePart>>;
+
+    /// Get `len` bytes of the serialized part described by `desc`, starting at `offset`.
+    ///
+    /// Allows the transfer of a large part to be resumed from where it stopped.
+    /// By default, ranged retrieval is not supported.
+    fn get_part_range(
+        &self,
+        _desc: &Self::PartDescription,
+        _offset: usize,
+        _len: usize,
+    ) -> Result<Vec<u8>> {
+        Err(DivisibleStateError::NotSupported("Ranged part retrieval").into())
+    }
+
+    /// Much like `get_parts()`, but yields the parts one by one, so they can be
+    /// sent out without all being held in memory at once.
+    ///
+    /// By default this wraps `get_parts()`, so states that can produce parts
+    /// lazily should override it.
+    fn get_parts_streaming<'a>(
+        &'a self,
+        parts: &'a [Self::PartDescription],
+    ) -> Box<dyn Iterator<Item = Result<Self::StatePart>> + 'a>
+    where
+        Self::StatePart: 'a,
+    {
+        match self.get_parts(parts) {
+            Ok(parts) => Box::new(parts.into_iter().map(Ok)),
+            Err(err) => Box::new(std::iter::once(Err(err))),
+        }
+    }
+}
+
+impl<S> InstallStateMessage<S>
+where
+    S: DivisibleState,
+{
+    /// The fraction (between 0 and 1) of the state that has been received,
+    /// if this is a progress report.
+    pub fn progress(&self) -> Option<f64> {
+        match self {
+            InstallStateMessage::Progress {
+                received_parts,
+                total_parts,
+            } => Some(progress_fraction(*received_parts, *total_parts)),
+            _ => None,
+        }
+    }
+}
+
+impl<S> AppState<S>
+where
+    S: DivisibleState,
+{
+    /// The parts carried by this message, if any
+    pub fn parts(&self) -> Option<&MaybeVec<S::StatePart>> {
+        match self {
+            AppState::StatePart(parts) | AppState::StatePartFinal(parts) => Some(parts),
+            _ => None,
+        }
+    }
+
+    /// Whether this is the last chunk of parts of the checkpoint
+    pub fn is_final_part(&self) -> bool {
+        matches!(self, AppState::StatePartFinal(_))
+    }
+
+    /// Whether more parts of the checkpoint should be expected after this message
+    pub fn expects_more_parts(&self) -> bool {
+        matches!(self, AppState::StateDescriptor(_) | AppState::StatePart(_))
+    }
+
+    /// Whether this confirms that the previous checkpoint is still current
+    pub fn is_no_change(&self) -> bool {
+        matches!(self, AppState::NoChange)
+    }
 }
 
 impl<S> AppStateMessage<S>
@@ -91,10 +560,54 @@ where
     pub fn new(seq_no: SeqNo, state_portion: AppState<S>) -> Self {
         AppStateMessage {
             seq_no,
+            part_sequence: None,
+            created_at: None,
             state: state_portion,
         }
     }
 
+    /// Creates a message which is the `part_sequence`th message of the checkpoint `seq_no`,
+    /// so the messages of the checkpoint can be reassembled if received out of order.
+    pub fn new_with_part_sequence(
+        seq_no: SeqNo,
+        part_sequence: SeqNo,
+        state_portion: AppState<S>,
+    ) -> Self {
+        AppStateMessage {
+            seq_no,
+            part_sequence: Some(part_sequence),
+            created_at: None,
+            state: state_portion,
+        }
+    }
+
+    pub fn part_sequence(&self) -> Option<SeqNo> {
+        self.part_sequence
+    }
+
+    /// Tags this message with the wall clock time at which the checkpoint was created.
+    pub fn with_created_at(mut self, created_at: SystemTime) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    pub fn created_at(&self) -> Option<SystemTime> {
+        self.created_at
+    }
+
+    /// How long ago the checkpoint was created, if it was tagged with its creation time
+    /// and the clock has not gone backwards since.
+    pub fn age(&self) -> Option<Duration> {
+        self.created_at
+            .and_then(|created_at| created_at.elapsed().ok())
+    }
+
+    /// The key by which messages should be sorted to reassemble checkpoints:
+    /// first by checkpoint sequence number, then by the position within the checkpoint.
+    pub fn ordering_key(&self) -> (SeqNo, Option<SeqNo>) {
+        (self.seq_no, self.part_sequence)
+    }
+
     pub fn into_state(self) -> (SeqNo, AppState<S>) {
         (self.seq_no, self.state)
     }
@@ -108,3 +621,997 @@ where
         self.seq_no
     }
 }
+
+impl<P, S> CheckpointExecutionResult<P, S>
+where
+    S: DivisibleState,
+{
+    pub fn new(replies: BatchReplies<P>, state: AppState<S>) -> Self {
+        CheckpointExecutionResult { replies, state }
+    }
+
+    pub fn replies(&self) -> &BatchReplies<P> {
+        &self.replies
+    }
+
+    pub fn state(&self) -> &AppState<S> {
+        &self.state
+    }
+
+    pub fn into_inner(self) -> (BatchReplies<P>, AppState<S>) {
+        (self.replies, self.state)
+    }
+}
+
+impl<S> StateDiff<S>
+where
+    S: DivisibleState,
+{
+    pub fn new(
+        added: Vec<S::PartDescription>,
+        removed: Vec<S::PartDescription>,
+        modified: Vec<S::PartDescription>,
+    ) -> Self {
+        StateDiff {
+            added,
+            removed,
+            modified,
+        }
+    }
+
+    /// The parts that are present in the other state, but not in ours
+    pub fn added(&self) -> &[S::PartDescription] {
+        &self.added
+    }
+
+    /// The parts that are present in our state, but not in the other
+    pub fn removed(&self) -> &[S::PartDescription] {
+        &self.removed
+    }
+
+    /// The parts that are present in both states, but differ (as described by the other state)
+    pub fn modified(&self) -> &[S::PartDescription] {
+        &self.modified
+    }
+
+    /// Whether both states are the same
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest_of(data: &[u8]) -> Digest {
+        let mut ctx = Context::new();
+
+        ctx.update(data);
+
+        ctx.finish()
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    #[cfg_attr(
+        feature = "serialize_serde",
+        derive(serde::Serialize, serde::Deserialize)
+    )]
+    struct TestPartId {
+        index: usize,
+        len: usize,
+        digest: Digest,
+    }
+
+    impl PartialOrd for TestPartId {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            self.index.partial_cmp(&other.index)
+        }
+    }
+
+    impl PartId for TestPartId {
+        fn content_description(&self) -> Digest {
+            self.digest
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    #[cfg_attr(
+        feature = "serialize_serde",
+        derive(serde::Serialize, serde::Deserialize)
+    )]
+    struct TestDescriptor {
+        seq: SeqNo,
+        version: u32,
+        parts: Vec<TestPartId>,
+    }
+
+    impl Orderable for TestDescriptor {
+        fn sequence_number(&self) -> SeqNo {
+            self.seq
+        }
+    }
+
+    impl DivisibleStateDescriptor<TestState> for TestDescriptor {
+        fn parts(&self) -> &Vec<TestPartId> {
+            &self.parts
+        }
+
+        fn compare_descriptors(&self, other: &Self) -> Vec<TestPartId> {
+            other
+                .parts
+                .iter()
+                .filter(|part| !self.parts.contains(part))
+                .cloned()
+                .collect()
+        }
+
+        /// The last part plays the role of an index, so it is fetched first
+        fn prioritize(&self, parts: &[TestPartId]) -> Vec<TestPartId> {
+            let last = self.parts.len().saturating_sub(1);
+
+            let (index, mut rest): (Vec<_>, Vec<_>) =
+                parts.iter().cloned().partition(|part| part.index == last);
+
+            rest.splice(0..0, index);
+
+            rest
+        }
+
+        /// The amount of bytes of the differing parts
+        fn delta_size_hint(&self, other: &Self) -> usize {
+            self.compare_descriptors(other)
+                .iter()
+                .map(|part| part.len)
+                .sum()
+        }
+
+        fn total_size_hint(&self) -> Option<usize> {
+            Some(self.parts.iter().map(|part| part.len).sum())
+        }
+
+        fn state_version(&self) -> u32 {
+            self.version
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    #[cfg_attr(
+        feature = "serialize_serde",
+        derive(serde::Serialize, serde::Deserialize)
+    )]
+    struct TestPart {
+        id: TestPartId,
+        data: Vec<u8>,
+    }
+
+    impl StatePart<TestState> for TestPart {
+        fn descriptor(&self) -> TestPartId {
+            self.id.clone()
+        }
+
+        fn serialize_part(&self) -> Result<Vec<u8>> {
+            Ok(self.data.clone())
+        }
+    }
+
+    /// A state made of byte blobs, each of them being a part.
+    ///
+    /// Version 1 of the format stores the blobs in upper case, while version 0 does not.
+    #[derive(Clone, Debug, PartialEq)]
+    struct TestState {
+        descriptor: TestDescriptor,
+        version: u32,
+        compression: CompressionKind,
+        parts: Vec<Vec<u8>>,
+    }
+
+    impl TestState {
+        fn with_parts(parts: &[&[u8]]) -> Self {
+            let mut state = TestState {
+                descriptor: TestDescriptor {
+                    seq: SeqNo::ZERO,
+                    version: 0,
+                    parts: vec![],
+                },
+                version: 0,
+                compression: CompressionKind::None,
+                parts: parts.iter().map(|part| part.to_vec()).collect(),
+            };
+
+            state.refresh_descriptor();
+
+            state
+        }
+
+        fn refresh_descriptor(&mut self) {
+            self.descriptor.version = self.version;
+            self.descriptor.parts = self
+                .parts
+                .iter()
+                .enumerate()
+                .map(|(index, data)| TestPartId {
+                    index,
+                    len: data.len(),
+                    digest: digest_of(data),
+                })
+                .collect();
+        }
+
+        fn part(&self, index: usize) -> TestPart {
+            TestPart {
+                id: self.descriptor.parts[index].clone(),
+                data: self.parts[index].clone(),
+            }
+        }
+    }
+
+    impl DivisibleState for TestState {
+        type PartDescription = TestPartId;
+        type StateDescriptor = TestDescriptor;
+        type StatePart = TestPart;
+
+        fn get_descriptor(&self) -> &TestDescriptor {
+            &self.descriptor
+        }
+
+        fn accept_parts(&mut self, parts: Vec<TestPart>) -> Result<()> {
+            for part in parts {
+                let index = part.id.index;
+
+                if index >= self.parts.len() {
+                    self.parts.resize(index + 1, vec![]);
+                }
+
+                self.parts[index] = part.data;
+            }
+
+            self.refresh_descriptor();
+
+            Ok(())
+        }
+
+        fn prepare_checkpoint(&mut self) -> Result<&TestDescriptor> {
+            self.descriptor.seq = self.descriptor.seq.next();
+            self.refresh_descriptor();
+
+            Ok(&self.descriptor)
+        }
+
+        fn get_parts(&self, parts: &[TestPartId]) -> Result<Vec<TestPart>> {
+            Ok(parts.iter().map(|part| self.part(part.index)).collect())
+        }
+
+        fn get_part_range(&self, desc: &TestPartId, offset: usize, len: usize) -> Result<Vec<u8>> {
+            let part = &self.parts[desc.index];
+
+            match offset.checked_add(len) {
+                Some(end) if end <= part.len() => Ok(part[offset..end].to_vec()),
+                _ => Err(anyhow::anyhow!(
+                    "Range {}+{} is out of the bounds of part {}",
+                    offset,
+                    len,
+                    desc.index
+                )),
+            }
+        }
+
+        fn clear(&mut self) -> Result<()> {
+            self.parts.clear();
+            self.refresh_descriptor();
+
+            Ok(())
+        }
+
+        /// Parts are tiny, so a couple of them are sent per message
+        fn transfer_chunk_size(&self) -> usize {
+            2
+        }
+
+        fn compression_algorithm(&self) -> CompressionKind {
+            self.compression
+        }
+
+        fn state_version(&self) -> u32 {
+            self.version
+        }
+
+        fn migrate_from(&mut self, version: u32) -> Result<()> {
+            match (version, self.version) {
+                (0, 1) => {
+                    self.parts
+                        .iter_mut()
+                        .for_each(|part| part.make_ascii_uppercase());
+                    self.refresh_descriptor();
+
+                    Ok(())
+                }
+                (from, current) if from != current => {
+                    Err(DivisibleStateError::UnsupportedMigration { from, current }.into())
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn progress_reports_the_received_fraction() {
+        let progress = InstallStateMessage::<TestState>::Progress {
+            received_parts: 3,
+            total_parts: 4,
+        };
+
+        assert!(matches!(
+            progress,
+            InstallStateMessage::Progress {
+                received_parts: 3,
+                total_parts: 4
+            }
+        ));
+        assert_eq!(progress.progress(), Some(0.75));
+
+        assert!(matches!(
+            InstallStateMessage::<TestState>::Done,
+            InstallStateMessage::Done
+        ));
+        assert_eq!(InstallStateMessage::<TestState>::Done.progress(), None);
+    }
+
+    /// A minimal stand-in for the executor's side of an installation, snapshotting the
+    /// state when the descriptor arrives and reverting to it on cancellation
+    fn install(state: &mut TestState, messages: Vec<InstallStateMessage<TestState>>) -> Result<()> {
+        let mut before_install = None;
+        let mut incoming_version = state.state_version();
+
+        for message in messages {
+            match message {
+                InstallStateMessage::StateDescriptor(descriptor) => {
+                    before_install = Some(state.clone());
+                    incoming_version = descriptor.state_version();
+                }
+                InstallStateMessage::StatePart(parts) => {
+                    state.accept_parts(parts.into_iter().collect())?
+                }
+                InstallStateMessage::Cancel { .. } => {
+                    if let Some(before_install) = before_install.take() {
+                        *state = before_install;
+                    }
+                }
+                InstallStateMessage::Done if incoming_version != state.state_version() => {
+                    state.migrate_from(incoming_version)?
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// An installation may be cancelled between any two calls to `accept_parts()`, so
+    /// after each of them the state must describe exactly the parts it holds and still
+    /// take back the parts it had before the installation started
+    #[test]
+    fn accepted_parts_leave_the_state_revertible() {
+        let mut state = TestState::with_parts(&[b"a", b"b"]);
+
+        let before_install = state.get_parts(state.get_descriptor().parts()).unwrap();
+        let before_descriptor = state.get_descriptor().clone();
+
+        let incoming = TestState::with_parts(&[b"c", b"d", b"e"]);
+
+        for index in [2, 0] {
+            state.accept_parts(vec![incoming.part(index)]).unwrap();
+
+            let held = state.get_parts(state.get_descriptor().parts()).unwrap();
+
+            assert!(held.iter().all(StatePart::verify_digest));
+
+            let mut reverted = state.clone();
+
+            reverted.clear().unwrap();
+            reverted.accept_parts(before_install.clone()).unwrap();
+
+            assert_eq!(reverted.get_descriptor(), &before_descriptor);
+        }
+    }
+
+    #[test]
+    fn delta_size_hint_can_be_overridden() {
+        let ours = TestState::with_parts(&[b"aa", b"bbb", b"c"]);
+        let theirs = TestState::with_parts(&[b"aa", b"xxxxx", b"y"]);
+
+        let (ours, theirs) = (ours.get_descriptor(), theirs.get_descriptor());
+
+        assert_eq!(ours.compare_descriptors(theirs).len(), 2);
+        assert_eq!(ours.delta_size_hint(theirs), 6);
+        assert_eq!(ours.delta_size_hint(ours), 0);
+    }
+
+    #[test]
+    fn tampered_parts_are_rejected() {
+        let source = TestState::with_parts(&[b"good", b"also good"]);
+        let mut state = TestState::with_parts(&[]);
+
+        let good = source.part(0);
+        let mut tampered = source.part(1);
+        tampered.data[0] ^= 0xFF;
+
+        assert!(good.verify_digest());
+        assert!(!tampered.verify_digest());
+
+        let err = state
+            .accept_parts_verified(vec![good.clone(), tampered])
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<DivisibleStateError>(),
+            Some(DivisibleStateError::PartDigestMismatch { index: 1, .. })
+        ));
+        assert!(state.parts.is_empty());
+
+        state.accept_parts_verified(vec![good]).unwrap();
+
+        assert_eq!(state.parts, vec![b"good".to_vec()]);
+    }
+
+    #[test]
+    fn streaming_parts_match_get_parts() {
+        let state = TestState::with_parts(&[b"a", b"bb", b"ccc"]);
+        let descriptions = vec![
+            state.descriptor.parts[2].clone(),
+            state.descriptor.parts[0].clone(),
+        ];
+
+        let parts = state.get_parts(&descriptions).unwrap();
+        let streamed = state
+            .get_parts_streaming(&descriptions)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(streamed, parts);
+        assert_eq!(streamed[0].data, b"ccc");
+    }
+
+    #[test]
+    fn progress_matches_the_monolithic_install() {
+        use crate::state::monolithic_state::InstallStateControl;
+
+        for (received, total) in [(0, 0), (0, 10), (3, 4), (4, 4), (7, 4)] {
+            let divisible = InstallStateMessage::<TestState>::Progress {
+                received_parts: received,
+                total_parts: total,
+            };
+            let monolithic = InstallStateControl::Progress {
+                received_bytes: received,
+                total_bytes: total,
+            };
+
+            assert_eq!(divisible.progress(), monolithic.progress());
+        }
+
+        let cancelled = |reason: &str| {
+            (
+                InstallStateMessage::<TestState>::Cancel {
+                    reason: reason.to_string(),
+                },
+                InstallStateControl::Cancel {
+                    reason: reason.to_string(),
+                },
+            )
+        };
+
+        assert!(matches!(
+            cancelled("Newer checkpoint"),
+            (
+                InstallStateMessage::Cancel { .. },
+                InstallStateControl::Cancel { .. }
+            )
+        ));
+    }
+
+    #[test]
+    fn checkpoint_execution_result_pairs_replies_with_state() {
+        let mut replies = BatchReplies::with_capacity(1);
+        replies.add(
+            atlas_common::node_id::NodeId(1),
+            SeqNo::ZERO,
+            SeqNo::ONE,
+            7u64,
+        );
+
+        let result: CheckpointExecutionResult<u64, TestState> =
+            CheckpointExecutionResult::new(replies, AppState::NoChange);
+
+        assert_eq!(result.replies().len(), 1);
+        assert!(result.state().is_no_change());
+
+        let (replies, state) = result.into_inner();
+
+        assert_eq!(replies.into_inner().remove(0).into_inner().3, 7);
+        assert!(matches!(state, AppState::NoChange));
+    }
+
+    #[test]
+    fn part_ranges_are_sub_slices() {
+        let state = TestState::with_parts(&[b"0123456789"]);
+        let desc = &state.descriptor.parts[0];
+
+        assert_eq!(state.get_part_range(desc, 2, 3).unwrap(), b"234");
+        assert_eq!(state.get_part_range(desc, 7, 3).unwrap(), b"789");
+        assert!(state.get_part_range(desc, 8, 3).is_err());
+        assert!(state.get_part_range(desc, usize::MAX, 2).is_err());
+    }
+
+    #[test]
+    fn prioritize_front_loads_parts() {
+        let state = TestState::with_parts(&[b"a", b"b", b"c", b"index"]);
+        let descriptor = state.get_descriptor();
+
+        let order: Vec<_> = descriptor
+            .prioritize(descriptor.parts())
+            .iter()
+            .map(|part| part.index)
+            .collect();
+
+        assert_eq!(order, vec![3, 0, 1, 2]);
+    }
+
+    #[test]
+    fn uncompressed_parts_round_trip() {
+        let state = TestState::with_parts(&[b"some part"]);
+        let part = state.part(0);
+
+        let compressed = part.compressed_with(CompressionKind::None).unwrap();
+
+        assert_eq!(
+            CompressionKind::None.decompress(&compressed, 1024).unwrap(),
+            part.serialize_part().unwrap()
+        );
+
+        let err = CompressionKind::None
+            .decompress(&compressed, 4)
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<DivisibleStateError>(),
+            Some(DivisibleStateError::PartTooLarge { size: 9, max: 4 })
+        ));
+    }
+
+    #[cfg(feature = "compression_lz4")]
+    #[test]
+    fn lz4_parts_round_trip() {
+        let state = TestState::with_parts(&[&[7u8; 4096]]);
+        let part = state.part(0);
+
+        let compressed = part.compressed_with(CompressionKind::Lz4).unwrap();
+
+        assert!(compressed.len() < 4096);
+        assert_eq!(
+            CompressionKind::Lz4.decompress(&compressed, 4096).unwrap(),
+            part.serialize_part().unwrap()
+        );
+    }
+
+    #[cfg(feature = "compression_lz4")]
+    #[test]
+    fn lz4_size_prefix_is_checked_before_decompressing() {
+        let mut forged = u32::MAX.to_le_bytes().to_vec();
+        forged.extend_from_slice(&[0; 16]);
+
+        let err = CompressionKind::Lz4.decompress(&forged, 1024).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<DivisibleStateError>(),
+            Some(DivisibleStateError::PartTooLarge { max: 1024, .. })
+        ));
+    }
+
+    #[test]
+    fn part_messages_sort_by_their_part_sequence() {
+        let checkpoint = SeqNo::from(3);
+
+        let mut messages: Vec<AppStateMessage<TestState>> = [2u32, 0, 1]
+            .into_iter()
+            .map(|part_seq| {
+                AppStateMessage::new_with_part_sequence(
+                    checkpoint,
+                    SeqNo::from(part_seq),
+                    AppState::NoChange,
+                )
+            })
+            .collect();
+
+        messages.push(AppStateMessage::new(SeqNo::from(2), AppState::Done));
+
+        messages.sort_by_key(AppStateMessage::ordering_key);
+
+        assert_eq!(
+            messages
+                .iter()
+                .map(AppStateMessage::ordering_key)
+                .collect::<Vec<_>>(),
+            vec![
+                (SeqNo::from(2), None),
+                (checkpoint, Some(SeqNo::from(0))),
+                (checkpoint, Some(SeqNo::from(1))),
+                (checkpoint, Some(SeqNo::from(2))),
+            ]
+        );
+
+        let (seq, state) = messages.pop().unwrap().into_state();
+
+        assert_eq!(seq, checkpoint);
+        assert!(state.is_no_change());
+    }
+
+    fn indexes(parts: &[TestPartId]) -> Vec<usize> {
+        parts.iter().map(|part| part.index).collect()
+    }
+
+    #[test]
+    fn diff_reports_added_parts() {
+        let ours = TestState::with_parts(&[b"a"]);
+        let theirs = TestState::with_parts(&[b"a", b"b", b"c"]);
+
+        let diff = ours.get_descriptor().diff(theirs.get_descriptor());
+
+        assert_eq!(indexes(diff.added()), vec![1, 2]);
+        assert!(diff.removed().is_empty());
+        assert!(diff.modified().is_empty());
+    }
+
+    #[test]
+    fn diff_reports_removed_parts() {
+        let ours = TestState::with_parts(&[b"a", b"b", b"c"]);
+        let theirs = TestState::with_parts(&[b"a"]);
+
+        let diff = ours.get_descriptor().diff(theirs.get_descriptor());
+
+        assert!(diff.added().is_empty());
+        assert_eq!(indexes(diff.removed()), vec![1, 2]);
+        assert!(diff.modified().is_empty());
+    }
+
+    #[test]
+    fn diff_reports_modified_parts_as_described_by_the_other_state() {
+        let ours = TestState::with_parts(&[b"a", b"b", b"c"]);
+        let theirs = TestState::with_parts(&[b"a", b"x", b"c"]);
+
+        let diff = ours.get_descriptor().diff(theirs.get_descriptor());
+
+        assert!(diff.added().is_empty());
+        assert!(diff.removed().is_empty());
+        assert_eq!(diff.modified(), &[theirs.part(1).id]);
+    }
+
+    #[test]
+    fn checkpoint_with_parts_returns_the_new_descriptor_and_its_parts() {
+        let mut state = TestState::with_parts(&[b"a", b"b"]);
+        state.parts[1] = b"changed".to_vec();
+
+        let (descriptor, parts) = state.prepare_checkpoint_with_parts().unwrap();
+
+        assert_eq!(descriptor.sequence_number(), SeqNo::ONE);
+        assert_eq!(descriptor.parts[1].digest, digest_of(b"changed"));
+
+        let ids = parts.iter().map(|part| part.id.clone()).collect::<Vec<_>>();
+        let data = parts
+            .iter()
+            .map(|part| part.data.clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(&ids, descriptor.parts());
+        assert_eq!(data, vec![b"a".to_vec(), b"changed".to_vec()]);
+    }
+
+    #[test]
+    fn checkpoints_can_be_streamed_across_messages() {
+        let state = TestState::with_parts(&[b"a", b"b", b"c", b"d"]);
+
+        let messages: Vec<AppState<TestState>> = vec![
+            AppState::StateDescriptor(state.get_descriptor().clone()),
+            AppState::StatePart(MaybeVec::from_many(vec![state.part(0), state.part(1)])),
+            AppState::StatePart(MaybeVec::from_one(state.part(2))),
+            AppState::StatePartFinal(MaybeVec::from_one(state.part(3))),
+            AppState::Done,
+        ];
+
+        let mut received = 0;
+        let mut finished = false;
+
+        for message in &messages {
+            if finished {
+                assert!(matches!(message, AppState::Done));
+                continue;
+            }
+
+            received += message.parts().map_or(0, MaybeVec::len);
+            finished = !message.expects_more_parts();
+
+            assert_eq!(finished, message.is_final_part());
+        }
+
+        assert!(finished);
+        assert_eq!(received, 4);
+    }
+
+    #[test]
+    fn messages_carry_their_creation_time() {
+        let created_at = SystemTime::now() - Duration::from_secs(5);
+
+        let message: AppStateMessage<TestState> =
+            AppStateMessage::new(SeqNo::ONE, AppState::Done).with_created_at(created_at);
+
+        assert_eq!(message.created_at(), Some(created_at));
+        assert!(message.age().unwrap() >= Duration::from_secs(5));
+
+        let (seq, _) = message.into_state();
+
+        assert_eq!(seq, SeqNo::ONE);
+
+        let untagged: AppStateMessage<TestState> = AppStateMessage::new(SeqNo::ONE, AppState::Done);
+
+        assert_eq!(untagged.created_at(), None);
+        assert_eq!(untagged.age(), None);
+    }
+
+    #[test]
+    fn checkpoint_delta_reports_only_changed_parts() {
+        let mut state = TestState::with_parts(&[b"a", b"b", b"c"]);
+
+        let previous = state.prepare_checkpoint().unwrap().clone();
+
+        state.parts[2] = b"changed".to_vec();
+
+        let (descriptor, changed) = state.prepare_checkpoint_delta(&previous).unwrap();
+
+        assert_eq!(
+            descriptor.sequence_number(),
+            previous.sequence_number().next()
+        );
+        assert_eq!(indexes(&changed), vec![2]);
+        assert_eq!(changed[0].digest, digest_of(b"changed"));
+    }
+
+    #[test]
+    fn total_size_hint_sums_the_parts() {
+        let state = TestState::with_parts(&[b"abc", b"de", &[0; 100]]);
+
+        assert_eq!(state.get_descriptor().total_size_hint(), Some(105));
+    }
+
+    fn install_messages_of(state: &TestState) -> Vec<InstallStateMessage<TestState>> {
+        let parts = (0..state.parts.len())
+            .map(|index| state.part(index))
+            .collect();
+
+        vec![
+            InstallStateMessage::StateDescriptor(state.get_descriptor().clone()),
+            InstallStateMessage::StatePart(MaybeVec::from_many(parts)),
+            InstallStateMessage::Done,
+        ]
+    }
+
+    #[test]
+    fn older_checkpoints_are_migrated_when_installed() {
+        let old = TestState::with_parts(&[b"ab", b"cd"]);
+
+        let mut state = TestState::with_parts(&[]);
+        state.version = 1;
+        state.refresh_descriptor();
+
+        install(&mut state, install_messages_of(&old)).unwrap();
+
+        assert_eq!(state.parts, vec![b"AB".to_vec(), b"CD".to_vec()]);
+        assert_eq!(state.get_descriptor().state_version(), 1);
+        assert_eq!(state.get_descriptor().parts[0].digest, digest_of(b"AB"));
+    }
+
+    #[test]
+    fn unsupported_migrations_fail_the_install() {
+        let mut newer = TestState::with_parts(&[b"ab"]);
+        newer.version = 2;
+        newer.refresh_descriptor();
+
+        let mut state = TestState::with_parts(&[]);
+
+        let err = install(&mut state, install_messages_of(&newer)).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<DivisibleStateError>(),
+            Some(DivisibleStateError::UnsupportedMigration {
+                from: 2,
+                current: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn no_change_confirms_the_previous_checkpoint() {
+        let message: AppStateMessage<TestState> =
+            AppStateMessage::new(SeqNo::from(4), AppState::NoChange);
+
+        let (seq, state) = message.into_state();
+
+        assert_eq!(seq, SeqNo::from(4));
+        assert!(state.is_no_change());
+        assert!(state.parts().is_none());
+        assert!(!state.expects_more_parts());
+        assert!(matches!(state, AppState::NoChange));
+    }
+
+    #[test]
+    fn identical_states_hash_equally() {
+        let state = TestState::with_parts(&[b"a", b"b", b"c"]);
+        let same = TestState::with_parts(&[b"a", b"b", b"c"]);
+        let different = TestState::with_parts(&[b"a", b"x", b"c"]);
+
+        assert_eq!(state.state_hash(), same.state_hash());
+        assert_ne!(state.state_hash(), different.state_hash());
+    }
+
+    #[test]
+    fn parts_are_sent_in_chunks_of_the_transfer_chunk_size() {
+        let state = TestState::with_parts(&[b"a", b"b", b"c", b"d", b"e"]);
+
+        let parts = state.get_parts(state.get_descriptor().parts()).unwrap();
+
+        let chunks = parts
+            .chunks(state.transfer_chunk_size())
+            .map(<[TestPart]>::len)
+            .collect::<Vec<_>>();
+
+        assert_eq!(state.transfer_chunk_size(), 2);
+        assert_eq!(chunks, vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn clearing_then_installing_yields_exactly_the_installed_parts() {
+        let mut state = TestState::with_parts(&[b"stale", b"parts", b"everywhere"]);
+        let other = TestState::with_parts(&[b"new"]);
+
+        state.clear().unwrap();
+
+        assert!(state.get_descriptor().parts().is_empty());
+
+        install(&mut state, install_messages_of(&other)).unwrap();
+
+        assert_eq!(state.parts, other.parts);
+        assert_eq!(
+            state.get_descriptor().parts(),
+            other.get_descriptor().parts()
+        );
+    }
+
+    #[test]
+    fn parts_are_compressed_uncompressed_by_default() {
+        let state = TestState::with_parts(&[b"plain part"]);
+        let part = state.part(0);
+
+        assert_eq!(part.compressed().unwrap(), b"plain part");
+        assert_eq!(state.compress_part(&part).unwrap(), b"plain part");
+    }
+
+    #[cfg(feature = "compression_lz4")]
+    #[test]
+    fn parts_are_compressed_with_the_algorithm_of_their_state() {
+        let mut state = TestState::with_parts(&[&[3u8; 2048]]);
+        state.compression = CompressionKind::Lz4;
+
+        let part = state.part(0);
+        let compressed = state.compress_part(&part).unwrap();
+
+        assert!(compressed.len() < 2048);
+        assert_eq!(
+            state
+                .compression_algorithm()
+                .decompress(&compressed, state.max_part_size())
+                .unwrap(),
+            part.serialize_part().unwrap()
+        );
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    #[cfg_attr(
+        feature = "serialize_serde",
+        derive(serde::Serialize, serde::Deserialize)
+    )]
+    struct PlainDescriptor {
+        seq: SeqNo,
+        parts: Vec<TestPartId>,
+    }
+
+    impl Orderable for PlainDescriptor {
+        fn sequence_number(&self) -> SeqNo {
+            self.seq
+        }
+    }
+
+    impl DivisibleStateDescriptor<PlainState> for PlainDescriptor {
+        fn parts(&self) -> &Vec<TestPartId> {
+            &self.parts
+        }
+
+        fn compare_descriptors(&self, _other: &Self) -> Vec<TestPartId> {
+            vec![]
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    #[cfg_attr(
+        feature = "serialize_serde",
+        derive(serde::Serialize, serde::Deserialize)
+    )]
+    struct PlainPart {
+        id: TestPartId,
+    }
+
+    impl StatePart<PlainState> for PlainPart {
+        fn descriptor(&self) -> TestPartId {
+            self.id.clone()
+        }
+    }
+
+    /// A state without parts which keeps the default `migrate_from()`
+    struct PlainState {
+        descriptor: PlainDescriptor,
+        version: u32,
+    }
+
+    impl PlainState {
+        fn with_version(version: u32) -> Self {
+            PlainState {
+                descriptor: PlainDescriptor {
+                    seq: SeqNo::ZERO,
+                    parts: vec![],
+                },
+                version,
+            }
+        }
+    }
+
+    impl DivisibleState for PlainState {
+        type PartDescription = TestPartId;
+        type StateDescriptor = PlainDescriptor;
+        type StatePart = PlainPart;
+
+        fn get_descriptor(&self) -> &PlainDescriptor {
+            &self.descriptor
+        }
+
+        fn accept_parts(&mut self, _parts: Vec<PlainPart>) -> Result<()> {
+            Ok(())
+        }
+
+        fn prepare_checkpoint(&mut self) -> Result<&PlainDescriptor> {
+            Ok(&self.descriptor)
+        }
+
+        fn get_parts(&self, _parts: &[TestPartId]) -> Result<Vec<PlainPart>> {
+            Ok(vec![])
+        }
+
+        fn state_version(&self) -> u32 {
+            self.version
+        }
+    }
+
+    #[test]
+    fn default_migration_only_accepts_the_current_version() {
+        let mut state = PlainState::with_version(1);
+
+        assert!(state.migrate_from(1).is_ok());
+
+        for from in [0, 2] {
+            let err = state.migrate_from(from).unwrap_err();
+
+            assert!(matches!(
+                err.downcast_ref::<DivisibleStateError>(),
+                Some(DivisibleStateError::UnsupportedMigration { from: f, current: 1 }) if *f == from
+            ));
+        }
+
+        assert!(PlainState::with_version(0).migrate_from(0).is_ok());
+    }
+}
@@ -1,2 +1,14 @@
 pub mod divisible_state;
 pub mod monolithic_state;
+
+/// The fraction (between 0 and 1) of a transfer of `total` units that has been
+/// completed after receiving `received` of them.
+///
+/// An empty transfer is complete, and receiving more than `total` is clamped to 1.
+pub(crate) fn progress_fraction(received: usize, total: usize) -> f64 {
+    if total == 0 {
+        1.0
+    } else {
+        received.min(total) as f64 / total as f64
+    }
+}
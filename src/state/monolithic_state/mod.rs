@@ -7,6 +7,9 @@ use atlas_common::serialization_helper::{NonSyncSerMsg, SerMsg};
 use serde::{Deserialize, Serialize};
 use std::io::{Read, Write};
 use std::mem::size_of;
+use std::time::{Duration, SystemTime};
+
+use crate::state::progress_fraction;
 
 /// The type abstraction for a monolithic state (only needs to be serializable, in reality)
 pub trait MonolithicState: NonSyncSerMsg {
@@ -31,11 +34,30 @@ where
     state: S,
 }
 
+/// Control messages to be sent from the state transfer module to the executor module,
+/// alongside the [`InstallStateMessage`] carrying the state itself
+pub enum InstallStateControl {
+    /// When the checkpoint being received was taken, to measure the lag of the
+    /// state transfer. It does not need to be acted upon.
+    CheckpointCreatedAt(SystemTime),
+    /// Report of how far the reception of the state has progressed
+    Progress {
+        received_bytes: usize,
+        total_bytes: usize,
+    },
+    /// The installation in progress has been abandoned (e.g. a newer checkpoint was received),
+    /// so the executor should keep its current state.
+    Cancel { reason: String },
+}
+
 pub struct AppStateMessage<S>
 where
     S: MonolithicState,
 {
     seq: SeqNo,
+    /// When the checkpoint was taken. Wall clock time, so it is still
+    /// meaningful once the message reaches another replica.
+    created_at: Option<SystemTime>,
     state: S,
 }
 
@@ -44,7 +66,27 @@ where
     S: MonolithicState,
 {
     pub fn new(seq: SeqNo, state: S) -> Self {
-        AppStateMessage { seq, state }
+        AppStateMessage {
+            seq,
+            created_at: None,
+            state,
+        }
+    }
+
+    /// Records when the checkpoint was taken.
+    pub fn with_created_at(mut self, created_at: SystemTime) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    pub fn created_at(&self) -> Option<SystemTime> {
+        self.created_at
+    }
+
+    /// The time elapsed since the checkpoint was taken, if known.
+    pub fn age(&self) -> Option<Duration> {
+        self.created_at
+            .and_then(|created_at| created_at.elapsed().ok())
     }
 
     pub fn seq(&self) -> SeqNo {
@@ -77,6 +119,20 @@ where
     }
 }
 
+impl InstallStateControl {
+    /// The fraction (between 0 and 1) of the state that has been received,
+    /// if this is a progress report.
+    pub fn progress(&self) -> Option<f64> {
+        match self {
+            InstallStateControl::Progress {
+                received_bytes,
+                total_bytes,
+            } => Some(progress_fraction(*received_bytes, *total_bytes)),
+            _ => None,
+        }
+    }
+}
+
 pub fn digest_state<S: MonolithicState>(appstate: &S) -> Result<Digest> {
     let mut state_vec = Vec::with_capacity(size_of::<S>());
 
@@ -90,3 +146,75 @@ pub fn digest_state<S: MonolithicState>(appstate: &S) -> Result<Digest> {
 
     Ok(digest)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    #[cfg_attr(
+        feature = "serialize_serde",
+        derive(serde::Serialize, serde::Deserialize)
+    )]
+    struct Counter(u64);
+
+    impl MonolithicState for Counter {
+        fn serialize_state<W>(mut w: W, request: &Self) -> Result<()>
+        where
+            W: Write,
+        {
+            Ok(w.write_all(&request.0.to_le_bytes())?)
+        }
+
+        fn deserialize_state<R>(mut r: R) -> Result<Self>
+        where
+            R: Read,
+            Self: Sized,
+        {
+            let mut bytes = [0; 8];
+            r.read_exact(&mut bytes)?;
+
+            Ok(Counter(u64::from_le_bytes(bytes)))
+        }
+    }
+
+    #[test]
+    fn install_message_keeps_its_accessors() {
+        let message = InstallStateMessage::new(Counter(42));
+
+        assert_eq!(message.state(), &Counter(42));
+        assert_eq!(message.into_state(), Counter(42));
+    }
+
+    #[test]
+    fn control_messages_report_progress() {
+        let progress = InstallStateControl::Progress {
+            received_bytes: 512,
+            total_bytes: 2048,
+        };
+
+        assert_eq!(progress.progress(), Some(0.25));
+
+        let cancel = InstallStateControl::Cancel {
+            reason: String::from("Newer checkpoint"),
+        };
+
+        assert_eq!(cancel.progress(), None);
+    }
+
+    #[test]
+    fn messages_carry_their_creation_time() {
+        let created_at = SystemTime::now() - Duration::from_secs(5);
+
+        let message = AppStateMessage::new(SeqNo::ONE, Counter(1)).with_created_at(created_at);
+
+        assert_eq!(message.created_at(), Some(created_at));
+        assert!(message.age().unwrap() >= Duration::from_secs(5));
+        assert_eq!(message.into_state(), Counter(1));
+
+        assert_eq!(
+            AppStateMessage::new(SeqNo::ONE, Counter(1)).created_at(),
+            None
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use std::io::{Read, Write};
+
+use atlas_common::error::*;
+
+use crate::app::{Application, Reply, Request};
+use crate::serialize::ApplicationData;
+
+/// The application data of the [`NoopApplication`].
+///
+/// Both requests and replies are plain `u64`s.
+pub struct NoopData;
+
+/// A trivial application, meant to test the execution pipeline.
+///
+/// The state is a `u64` counter, which every update increments, replying with
+/// the new value of the counter. The operation payload is ignored.
+pub struct NoopApplication;
+
+impl ApplicationData for NoopData {
+    type Request = u64;
+    type Reply = u64;
+
+    fn serialize_request<W>(w: W, request: &Self::Request) -> Result<()>
+    where
+        W: Write,
+    {
+        serialize_u64(w, *request)
+    }
+
+    fn deserialize_request<R>(r: R) -> Result<Self::Request>
+    where
+        R: Read,
+    {
+        deserialize_u64(r)
+    }
+
+    fn serialize_reply<W>(w: W, reply: &Self::Reply) -> Result<()>
+    where
+        W: Write,
+    {
+        serialize_u64(w, *reply)
+    }
+
+    fn deserialize_reply<R>(r: R) -> Result<Self::Reply>
+    where
+        R: Read,
+    {
+        deserialize_u64(r)
+    }
+}
+
+fn serialize_u64<W: Write>(mut w: W, value: u64) -> Result<()> {
+    w.write_all(&value.to_le_bytes())?;
+
+    Ok(())
+}
+
+fn deserialize_u64<R: Read>(mut r: R) -> Result<u64> {
+    let mut bytes = [0; 8];
+
+    r.read_exact(&mut bytes)?;
+
+    Ok(u64::from_le_bytes(bytes))
+}
+
+impl Application<u64> for NoopApplication {
+    type AppData = NoopData;
+
+    fn initial_state() -> Result<u64> {
+        Ok(0)
+    }
+
+    fn name(&self) -> &'static str {
+        "noop-application"
+    }
+
+    fn unordered_execution(&self, state: &u64, _request: Request<Self, u64>) -> Reply<Self, u64> {
+        *state
+    }
+
+    fn update(&self, state: &mut u64, _request: Request<Self, u64>) -> Reply<Self, u64> {
+        *state += 1;
+
+        *state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+
+    use crate::app::UpdateBatch;
+
+    use super::*;
+
+    #[test]
+    fn update_batch_counts_the_updates() {
+        let app = NoopApplication;
+        let mut state = NoopApplication::initial_state().unwrap();
+
+        let mut batch = UpdateBatch::new(SeqNo::ONE);
+
+        for op in 0..5 {
+            batch.add(NodeId(0), SeqNo::ZERO, SeqNo::from(op), u64::from(op) * 10);
+        }
+
+        let replies = app.update_batch(&mut state, batch);
+
+        assert_eq!(state, 5);
+        assert_eq!(
+            replies
+                .into_inner()
+                .into_iter()
+                .map(|reply| reply.into_inner().3)
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn noop_application_is_named() {
+        assert_eq!(NoopApplication.name(), "noop-application");
+    }
+}